@@ -5,15 +5,85 @@ use std::io::{self, Read};
 use std::str::FromStr;
 
 use byteorder::{BigEndian, ReadBytesExt};
+use flate2::bufread::ZlibDecoder;
+use num::bigint::{BigInt, Sign};
 use serde::de::{self, Visitor, DeserializeSeed};
 
 use crate::errors::{Error, Result};
-use crate::types::ETF_VERSION;
+use crate::term::read_term;
+use crate::term_ref::TermSkipper;
+use crate::types::{ETF_VERSION, check_bits_in_last_byte};
+
+
+/// Generates `deserialize_*` methods for the native integer types which
+/// accept any integer encoding, including bignums that fit into the type
+macro_rules! deserialize_integer {
+    ($method:ident, $visit:ident, $ty:ty) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+            self.ensure_header()?;
+            let value = match self.header.unwrap() {
+                97 | 98 => <$ty>::try_from(self.read_small_integer()?).ok(),
+                110 | 111 => <$ty>::try_from(&self.read_bignum()?).ok(),
+                _ => return self.parse_value(visitor),
+            };
+            let value = value.ok_or(Error::IntegerOverflow(stringify!($ty)))?;
+            visitor.$visit(value)
+        }
+    };
+}
+
+
+/// Check that the tag belongs to one of the atom encodings
+#[inline]
+fn is_atom_tag(tag: u8) -> bool {
+    matches!(tag, 82 | 100 | 115 | 118 | 119)
+}
+
+
+/// Convert character codes of an Erlang string into `String`. Codes are
+/// treated as UTF-8 bytes when they form a valid UTF-8 sequence (this is how
+/// strings are encoded by the `Serializer`), otherwise as code points
+fn codes_to_string(codes: Vec<u32>) -> Result<String> {
+    let bytes: Option<Vec<u8>> = codes.iter()
+        .map(|code| u8::try_from(*code).ok())
+        .collect();
+    if let Some(Ok(string)) = bytes.map(String::from_utf8) {
+        return Ok(string);
+    }
+
+    codes.into_iter()
+        .map(|code| {
+            char::from_u32(code)
+                .ok_or_else(|| Error::Custom(format!("invalid character code {}", code)))
+        })
+        .collect()
+}
+
+
+/// Options that control how terms are decoded by the `Deserializer`.
+///
+/// Erlang strings are lists of character codes, so decoding them into
+/// `String` is ambiguous: codes which form a valid UTF-8 sequence are taken
+/// as UTF-8 bytes, the way the `Serializer` writes strings, and as code
+/// points otherwise. A Latin-1 charlist like `[195, 169]` ("Ã©") is decoded
+/// as "é" then. Decode into `Vec<u8>` or `Vec<i32>` to get the codes as-is
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DeserializerOptions {
+    /// Decode bitstrings (BIT_BINARY_EXT) into bytes values, like `Vec<u8>`,
+    /// with the unused bits of the last byte as they were sent. Bitstrings
+    /// are rejected with `Error::UnexpectedBitstring` otherwise
+    pub accept_bitstrings: bool,
+    /// Atoms which are referenced by ATOM_CACHE_REF, usually taken from
+    /// the distribution header (see `dist::read_dist_header`). References
+    /// outside of the table are rejected
+    pub atom_cache_refs: Vec<String>,
+}
 
 
 pub struct Deserializer<R: Read> {
     reader: R,
     header: Option<u8>,
+    options: DeserializerOptions,
 }
 
 
@@ -29,9 +99,16 @@ impl<R: Read> Deserializer<R> {
     /// Creates the BERT parser from an `std::io::Read`
     #[inline]
     pub fn new(reader: R) -> Deserializer<R> {
+        Deserializer::with_options(reader, DeserializerOptions::default())
+    }
+
+    /// Creates the BERT parser with the specified decoding options
+    #[inline]
+    pub fn with_options(reader: R, options: DeserializerOptions) -> Deserializer<R> {
         Deserializer {
             reader,
             header: None,
+            options,
         }
     }
 
@@ -68,13 +145,54 @@ impl<R: Read> Deserializer<R> {
         Ok(string_buffer)
     }
 
+    #[inline]
+    fn read_bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut buffer = vec![0u8; len];
+        self.reader.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
     /// Read an atom value (after the tag byte has been consumed).
     /// Returns the atom string
     #[inline]
-    fn read_atom_value(&mut self) -> Result<String> {
-        let length = self.read_i16::<BigEndian>()?;
-        let string = self.read_string(length as usize)?;
-        Ok(string)
+    fn read_atom_value(&mut self, tag: u8) -> Result<String> {
+        match tag {
+            // ATOM_EXT is limited to Latin-1 characters
+            100 => {
+                let length = self.read_u16::<BigEndian>()? as usize;
+                let bytes = self.read_bytes(length)?;
+                Ok(bytes.iter().map(|&byte| byte as char).collect())
+            }
+            115 => {
+                let length = self.read_u8()? as usize;
+                let bytes = self.read_bytes(length)?;
+                Ok(bytes.iter().map(|&byte| byte as char).collect())
+            }
+            118 => {
+                let length = self.read_u16::<BigEndian>()? as usize;
+                let bytes = self.read_bytes(length)?;
+                Ok(String::from_utf8(bytes)?)
+            }
+            119 => {
+                let length = self.read_u8()? as usize;
+                let bytes = self.read_bytes(length)?;
+                Ok(String::from_utf8(bytes)?)
+            }
+            82 => {
+                let index = self.read_u8()? as usize;
+                self.options.atom_cache_refs.get(index).cloned().ok_or_else(|| {
+                    Error::Custom(format!("atom cache reference {} is not in the table", index))
+                })
+            }
+            _ => Err(Error::InvalidTag),
+        }
+    }
+
+    /// Read an atom with its tag. Fails if the next value is not an atom
+    #[inline]
+    fn read_atom(&mut self) -> Result<String> {
+        let tag = self.read_u8()?;
+        self.read_atom_value(tag)
     }
 
     #[inline]
@@ -83,9 +201,13 @@ impl<R: Read> Deserializer<R> {
         self.header = None;
         match header {
             70 | 99 => self.parse_float(header, visitor),
+            77 => self.parse_bitstring(visitor),
+            88 | 103 => self.parse_pid(header, visitor),
+            90 | 101 | 114 => self.parse_reference(header, visitor),
+            89 | 102 | 120 => self.parse_port(header, visitor),
             97 => self.parse_unsigned_integer(visitor),
             98 => self.parse_integer(visitor),
-            100 => self.parse_atom(visitor),
+            82 | 100 | 115 | 118 | 119 => self.parse_atom(header, visitor),
             104 => self.parse_small_tuple(visitor),
             105 => self.parse_large_tuple(visitor),
             106 => self.parse_nil(visitor),
@@ -94,6 +216,9 @@ impl<R: Read> Deserializer<R> {
             109 => self.parse_binary(visitor),
             110 => self.parse_small_bignum(visitor),
             111 => self.parse_large_bignum(visitor),
+            112 => self.parse_closure(visitor),
+            113 => self.parse_export_fun(visitor),
+            116 => self.parse_map(visitor),
             _ => Err(Error::InvalidTag)
         }
     }
@@ -109,8 +234,8 @@ impl<R: Read> Deserializer<R> {
             },
             99 => {
                 let float_str = self.read_string(31)?;
-                let value = f32::from_str(&float_str)?;
-                visitor.visit_f64(value as f64)
+                let value = f64::from_str(&float_str)?;
+                visitor.visit_f64(value)
             },
             _ => Err(Error::InvalidTag)
         }
@@ -132,9 +257,9 @@ impl<R: Read> Deserializer<R> {
 
     #[inline]
     fn parse_atom<'de, V: Visitor<'de>>(
-        &mut self, visitor: V
+        &mut self, header: u8, visitor: V
     ) -> Result<V::Value> {
-        let string = self.read_atom_value()?;
+        let string = self.read_atom_value(header)?;
         visitor.visit_string(string)
     }
 
@@ -142,11 +267,39 @@ impl<R: Read> Deserializer<R> {
     fn parse_string<'de, V: Visitor<'de>>(
         &mut self, visitor: V
     ) -> Result<V::Value> {
-        let length = self.read_i16::<BigEndian>()?;
-        let string = self.read_string(length as usize)?;
+        let length = self.read_u16::<BigEndian>()? as usize;
+        let bytes = self.read_bytes(length)?;
+        let string = codes_to_string(bytes.into_iter().map(u32::from).collect())?;
         visitor.visit_string(string)
     }
 
+    /// Parse STRING_EXT as a list of bytes
+    #[inline]
+    fn parse_string_bytes<'de, V: Visitor<'de>>(
+        &mut self, visitor: V
+    ) -> Result<V::Value> {
+        let length = self.read_u16::<BigEndian>()? as usize;
+        visitor.visit_seq(BinarySeqAccess::new(self, length))
+    }
+
+    /// Parse LIST_EXT of character codes as a string
+    #[inline]
+    fn parse_charlist<'de, V: Visitor<'de>>(
+        &mut self, visitor: V
+    ) -> Result<V::Value> {
+        let length = self.read_u32::<BigEndian>()? as usize;
+        let mut codes = Vec::with_capacity(length.min(4096));
+        for _ in 0..length {
+            let code = self.read_small_integer()?;
+            let code = u32::try_from(code).map_err(|_| Error::IntegerOverflow("char"))?;
+            codes.push(code);
+        }
+        if self.read_u8()? != 106 {
+            return Err(Error::ImproperList);
+        }
+        visitor.visit_string(codes_to_string(codes)?)
+    }
+
     #[inline]
     fn parse_binary<'de, V: Visitor<'de>>(
         &mut self, visitor: V
@@ -155,6 +308,40 @@ impl<R: Read> Deserializer<R> {
         visitor.visit_seq(BinarySeqAccess::new(self, length))
     }
 
+    /// Parse BIT_BINARY_EXT as plain bytes, when it's enabled by options
+    #[inline]
+    fn parse_bitstring<'de, V: Visitor<'de>>(
+        &mut self, visitor: V
+    ) -> Result<V::Value> {
+        if !self.options.accept_bitstrings {
+            return Err(Error::UnexpectedBitstring);
+        }
+        let length = self.read_u32::<BigEndian>()? as usize;
+        check_bits_in_last_byte(length, self.read_u8()?)?;
+        visitor.visit_seq(BinarySeqAccess::new(self, length))
+    }
+
+    /// Parse BIT_BINARY_EXT or BINARY_EXT for `BertBitString`. The bitstring
+    /// is presented as a seq of [bytes, bits_in_last_byte]
+    #[inline]
+    fn parse_bert_bitstring<'de, V: Visitor<'de>>(
+        &mut self, header: u8, visitor: V
+    ) -> Result<V::Value> {
+        let length = self.read_u32::<BigEndian>()? as usize;
+        let bits_in_last_byte = match header {
+            77 => self.read_u8()?,
+            _ => 8,
+        };
+        if header == 77 {
+            check_bits_in_last_byte(length, bits_in_last_byte)?;
+        }
+        let bytes = self.read_bytes(length)?;
+        visitor.visit_seq(ExtensionSeqAccess::new(vec![
+            ExtensionValue::Bytes(bytes),
+            ExtensionValue::U8(bits_in_last_byte),
+        ]))
+    }
+
     #[inline]
     fn parse_nil<'de, V: Visitor<'de>>(
         &mut self, visitor: V
@@ -171,7 +358,7 @@ impl<R: Read> Deserializer<R> {
         // Consume the trailing Nil byte after the list elements
         let tail = self.read_u8()?;
         if tail != 106 {
-            return Err(Error::InvalidTag);
+            return Err(Error::ImproperList);
         }
         Ok(result)
     }
@@ -192,6 +379,161 @@ impl<R: Read> Deserializer<R> {
         visitor.visit_seq(TupleSeqAccess::new(self, arity))
     }
 
+    /// Parse PID_EXT or NEW_PID_EXT. The pid is presented as a seq of
+    /// [node, id, serial, creation, tag, node_tag], so it can be encoded
+    /// back with the same tags
+    #[inline]
+    fn parse_pid<'de, V: Visitor<'de>>(
+        &mut self, header: u8, visitor: V
+    ) -> Result<V::Value> {
+        let node_tag = self.read_u8()?;
+        let node = self.read_atom_value(node_tag)?;
+        let id = self.read_u32::<BigEndian>()?;
+        let serial = self.read_u32::<BigEndian>()?;
+        let creation = match header {
+            103 => self.read_u8()? as u32,
+            _ => self.read_u32::<BigEndian>()?,
+        };
+        visitor.visit_seq(ExtensionSeqAccess::new(vec![
+            ExtensionValue::Atom(node),
+            ExtensionValue::U32(id),
+            ExtensionValue::U32(serial),
+            ExtensionValue::U32(creation),
+            ExtensionValue::U8(header),
+            ExtensionValue::U8(node_tag),
+        ]))
+    }
+
+    /// Parse REFERENCE_EXT, NEW_REFERENCE_EXT or NEWER_REFERENCE_EXT.
+    /// The reference is presented as a seq of [node, creation, id_words]
+    #[inline]
+    fn parse_reference<'de, V: Visitor<'de>>(
+        &mut self, header: u8, visitor: V
+    ) -> Result<V::Value> {
+        let (node, creation, id) = match header {
+            101 => {
+                let node = self.read_atom()?;
+                let id = self.read_u32::<BigEndian>()?;
+                let creation = self.read_u8()? as u32;
+                (node, creation, vec![id])
+            }
+            _ => {
+                let length = self.read_u16::<BigEndian>()? as usize;
+                let node = self.read_atom()?;
+                let creation = match header {
+                    114 => self.read_u8()? as u32,
+                    _ => self.read_u32::<BigEndian>()?,
+                };
+                let mut id = Vec::with_capacity(length);
+                for _ in 0..length {
+                    id.push(self.read_u32::<BigEndian>()?);
+                }
+                (node, creation, id)
+            }
+        };
+        visitor.visit_seq(ExtensionSeqAccess::new(vec![
+            ExtensionValue::Atom(node),
+            ExtensionValue::U32(creation),
+            ExtensionValue::Words(id),
+        ]))
+    }
+
+    /// Parse PORT_EXT, NEW_PORT_EXT or V4_PORT_EXT. The port is presented
+    /// as a seq of [node, id, creation]
+    #[inline]
+    fn parse_port<'de, V: Visitor<'de>>(
+        &mut self, header: u8, visitor: V
+    ) -> Result<V::Value> {
+        let node = self.read_atom()?;
+        let (id, creation) = match header {
+            102 => {
+                let id = self.read_u32::<BigEndian>()? as u64;
+                (id, self.read_u8()? as u32)
+            }
+            89 => {
+                let id = self.read_u32::<BigEndian>()? as u64;
+                (id, self.read_u32::<BigEndian>()?)
+            }
+            _ => {
+                let id = self.read_u64::<BigEndian>()?;
+                (id, self.read_u32::<BigEndian>()?)
+            }
+        };
+        visitor.visit_seq(ExtensionSeqAccess::new(vec![
+            ExtensionValue::Atom(node),
+            ExtensionValue::U64(id),
+            ExtensionValue::U32(creation),
+        ]))
+    }
+
+    /// Parse NEW_FUN_EXT. The closure is presented as a seq of
+    /// [arity, uniq, index, module, old_index, old_uniq, pid, free_vars],
+    /// where all terms are kept as raw encoded bytes
+    #[inline]
+    fn parse_closure<'de, V: Visitor<'de>>(
+        &mut self, visitor: V
+    ) -> Result<V::Value> {
+        // The size includes the size field itself
+        let size = self.read_u32::<BigEndian>()? as usize;
+        let length = size.checked_sub(4).ok_or(Error::EndOfStream)?;
+        let body = self.read_bytes(length)?;
+
+        let mut skipper = TermSkipper::new(&body);
+        let arity = skipper.read_u8()?;
+        let uniq = skipper.read_slice(16)?.to_vec();
+        let index = skipper.read_u32()?;
+        let num_free = skipper.read_u32()?;
+        let module = skipper.read_raw_term()?.to_vec();
+        let old_index = skipper.read_raw_term()?.to_vec();
+        let old_uniq = skipper.read_raw_term()?.to_vec();
+        let pid = skipper.read_raw_term()?.to_vec();
+        let mut free_vars = Vec::new();
+        for _ in 0..num_free {
+            free_vars.push(skipper.read_raw_term()?.to_vec());
+        }
+        if !skipper.is_empty() {
+            return Err(Error::Custom("closure size doesn't match its contents".to_string()));
+        }
+
+        visitor.visit_seq(ExtensionSeqAccess::new(vec![
+            ExtensionValue::U8(arity),
+            ExtensionValue::Bytes(uniq),
+            ExtensionValue::U32(index),
+            ExtensionValue::Bytes(module),
+            ExtensionValue::Bytes(old_index),
+            ExtensionValue::Bytes(old_uniq),
+            ExtensionValue::Bytes(pid),
+            ExtensionValue::Terms(free_vars),
+        ]))
+    }
+
+    /// Parse EXPORT_EXT. The fun is presented as a seq of
+    /// [module, function, arity]
+    #[inline]
+    fn parse_export_fun<'de, V: Visitor<'de>>(
+        &mut self, visitor: V
+    ) -> Result<V::Value> {
+        let module = self.read_atom()?;
+        let function = self.read_atom()?;
+        if self.read_u8()? != 97 {
+            return Err(Error::InvalidTag);
+        }
+        let arity = self.read_u8()?;
+        visitor.visit_seq(ExtensionSeqAccess::new(vec![
+            ExtensionValue::Atom(module),
+            ExtensionValue::Atom(function),
+            ExtensionValue::U8(arity),
+        ]))
+    }
+
+    #[inline]
+    fn parse_map<'de, V: Visitor<'de>>(
+        &mut self, visitor: V
+    ) -> Result<V::Value> {
+        let arity = self.read_u32::<BigEndian>()? as usize;
+        visitor.visit_map(NativeMapAccess::new(self, arity))
+    }
+
     #[inline]
     fn parse_small_bignum<'de, V: Visitor<'de>>(
         &mut self, visitor: V
@@ -208,6 +550,36 @@ impl<R: Read> Deserializer<R> {
         self.parse_bignum_body(n, visitor)
     }
 
+    /// Read SMALL_INTEGER_EXT or INTEGER_EXT value, including the tag
+    #[inline]
+    fn read_small_integer(&mut self) -> Result<i64> {
+        let tag = self.ensure_header()?;
+        self.header = None;
+        match tag {
+            97 => Ok(self.read_u8()? as i64),
+            98 => Ok(self.read_i32::<BigEndian>()? as i64),
+            _ => Err(Error::InvalidTag),
+        }
+    }
+
+    /// Read SMALL_BIG_EXT or LARGE_BIG_EXT value, including the tag
+    #[inline]
+    fn read_bignum(&mut self) -> Result<BigInt> {
+        let tag = self.ensure_header()?;
+        self.header = None;
+        let n = match tag {
+            110 => self.read_u8()? as usize,
+            111 => self.read_u32::<BigEndian>()? as usize,
+            _ => return Err(Error::InvalidTag),
+        };
+        let sign = match self.read_u8()? {
+            0 => Sign::Plus,
+            _ => Sign::Minus,
+        };
+        let magnitude = self.read_bytes(n)?;
+        Ok(BigInt::from_bytes_le(sign, &magnitude))
+    }
+
     #[inline]
     fn parse_bignum_body<'de, V: Visitor<'de>>(
         &mut self, n: usize, visitor: V
@@ -228,11 +600,7 @@ impl<R: Read> Deserializer<R> {
         &mut self, visitor: V
     ) -> Result<V::Value> {
         // Read the second element which should be the atom "true" or "false"
-        let tag = self.read_u8()?;
-        if tag != 100 {
-            return Err(Error::InvalidTag);
-        }
-        let atom = self.read_atom_value()?;
+        let atom = self.read_atom()?;
         match atom.as_str() {
             "true" => visitor.visit_bool(true),
             "false" => visitor.visit_bool(false),
@@ -267,7 +635,7 @@ impl<R: Read> Deserializer<R> {
 }
 
 
-impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
+impl<'de, R: Read> de::Deserializer<'de> for &mut Deserializer<R> {
     type Error = Error;
 
     #[inline]
@@ -284,8 +652,8 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
             if arity >= 2 {
                 // Read first element tag
                 let first_tag = self.read_u8()?;
-                if first_tag == 100 {
-                    let atom = self.read_atom_value()?;
+                if is_atom_tag(first_tag) {
+                    let atom = self.read_atom_value(first_tag)?;
                     if atom == "bert" {
                         // This is a special BERT type
                         if arity == 2 {
@@ -294,8 +662,8 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                         } else if arity == 3 {
                             // Could be {bert, dict, ...}
                             let tag2 = self.read_u8()?;
-                            if tag2 == 100 {
-                                let atom2 = self.read_atom_value()?;
+                            if is_atom_tag(tag2) {
+                                let atom2 = self.read_atom_value(tag2)?;
                                 if atom2 == "dict" {
                                     return self.parse_bert_dict(visitor);
                                 }
@@ -305,8 +673,8 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                             // Could be {bert, time, mega, sec, micro}
                             // Consume the "time" atom, then read 3 i32 values as a seq
                             let tag2 = self.read_u8()?;
-                            if tag2 == 100 {
-                                let atom2 = self.read_atom_value()?;
+                            if is_atom_tag(tag2) {
+                                let atom2 = self.read_atom_value(tag2)?;
                                 if atom2 == "time" {
                                     return visitor.visit_seq(TupleSeqAccess::new(self, 3));
                                 }
@@ -315,8 +683,8 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                         } else if arity == 4 {
                             // Could be {bert, regex, source, options}
                             let tag2 = self.read_u8()?;
-                            if tag2 == 100 {
-                                let atom2 = self.read_atom_value()?;
+                            if is_atom_tag(tag2) {
+                                let atom2 = self.read_atom_value(tag2)?;
                                 if atom2 == "regex" {
                                     return visitor.visit_seq(TupleSeqAccess::new(self, 2));
                                 }
@@ -356,11 +724,7 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                 return Err(Error::InvalidTag);
             }
             // Read "bert" atom
-            let tag = self.read_u8()?;
-            if tag != 100 {
-                return Err(Error::InvalidTag);
-            }
-            let atom = self.read_atom_value()?;
+            let atom = self.read_atom()?;
             if atom != "bert" {
                 return Err(Error::Custom(format!("expected bert atom, got {}", atom)));
             }
@@ -382,9 +746,44 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
         }
     }
 
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        // Erlang strings are lists of character codes, and the empty
+        // string is the empty list
+        match self.ensure_header()? {
+            106 => {
+                self.header = None;
+                visitor.visit_string(String::new())
+            }
+            108 => {
+                self.header = None;
+                self.parse_charlist(visitor)
+            }
+            _ => self.parse_value(visitor),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
     fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.ensure_header()?;
-        self.parse_value(visitor)
+        match self.ensure_header()? {
+            // Erlang writes the empty list as NIL_EXT
+            106 => {
+                self.header = None;
+                visitor.visit_seq(ListSeqAccess::new(self, 0))
+            }
+            // STRING_EXT is a list of bytes, encoded in a compact way
+            107 => {
+                self.header = None;
+                self.parse_string_bytes(visitor)
+            }
+            _ => self.parse_value(visitor),
+        }
     }
 
     fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
@@ -393,9 +792,23 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     }
 
     fn deserialize_tuple_struct<V: Visitor<'de>>(
-        self, _name: &'static str, _len: usize, visitor: V
+        self, name: &'static str, _len: usize, visitor: V
     ) -> Result<V::Value> {
-        self.ensure_header()?;
+        use crate::wrappers::IMPROPER_LIST_STRUCT_NAME;
+
+        let header = self.ensure_header()?;
+        if name == IMPROPER_LIST_STRUCT_NAME {
+            // Elements are followed by the tail, while any other term is
+            // a list without elements
+            return match header {
+                108 => {
+                    self.header = None;
+                    let length = self.read_u32::<BigEndian>()? as usize;
+                    visitor.visit_seq(ListSeqAccess::new(self, length + 1))
+                }
+                _ => visitor.visit_seq(ListSeqAccess::new(self, 1)),
+            };
+        }
         self.parse_value(visitor)
     }
 
@@ -404,22 +817,22 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
         let header = self.header.unwrap();
         self.header = None;
 
+        if header == 116 {
+            return self.parse_map(visitor);
+        }
+
         if header == 104 {
             let arity = self.read_u8()?;
             if arity != 3 {
                 return Err(Error::InvalidTag);
             }
             // Read "bert" atom
-            let tag = self.read_u8()?;
-            if tag != 100 { return Err(Error::InvalidTag); }
-            let atom = self.read_atom_value()?;
+            let atom = self.read_atom()?;
             if atom != "bert" {
                 return Err(Error::Custom(format!("expected bert atom, got {}", atom)));
             }
             // Read "dict" atom
-            let tag = self.read_u8()?;
-            if tag != 100 { return Err(Error::InvalidTag); }
-            let atom = self.read_atom_value()?;
+            let atom = self.read_atom()?;
             if atom != "dict" {
                 return Err(Error::Custom(format!("expected dict atom, got {}", atom)));
             }
@@ -431,7 +844,11 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     fn deserialize_struct<V: Visitor<'de>>(
         self, name: &'static str, _fields: &'static [&'static str], visitor: V
     ) -> Result<V::Value> {
-        use crate::wrappers::{TIME_STRUCT_NAME, REGEX_STRUCT_NAME, BIGNUM_STRUCT_NAME};
+        use crate::wrappers::{
+            TIME_STRUCT_NAME, REGEX_STRUCT_NAME, BIGNUM_STRUCT_NAME,
+            PID_STRUCT_NAME, REFERENCE_STRUCT_NAME, PORT_STRUCT_NAME,
+            BITSTRING_STRUCT_NAME, EXPORT_FUN_STRUCT_NAME, CLOSURE_STRUCT_NAME
+        };
 
         self.ensure_header()?;
         let header = self.header.unwrap();
@@ -445,13 +862,9 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                 if header == 104 {
                     let arity = self.read_u8()? as usize;
                     // Skip "bert" atom
-                    let tag = self.read_u8()?;
-                    if tag != 100 { return Err(Error::InvalidTag); }
-                    let _bert = self.read_atom_value()?;
+                    let _bert = self.read_atom()?;
                     // Skip type atom (time/regex)
-                    let tag = self.read_u8()?;
-                    if tag != 100 { return Err(Error::InvalidTag); }
-                    let _type_atom = self.read_atom_value()?;
+                    let _type_atom = self.read_atom()?;
                     // Remaining elements are the fields
                     let remaining = arity - 2;
                     return visitor.visit_seq(TupleSeqAccess::new(self, remaining));
@@ -462,6 +875,43 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                 // Bignum is encoded as newtype_struct with bytes
                 self.deserialize_newtype_struct(name, visitor)
             }
+            PID_STRUCT_NAME => {
+                match header {
+                    88 | 103 => self.parse_value(visitor),
+                    _ => Err(Error::InvalidTag),
+                }
+            }
+            REFERENCE_STRUCT_NAME => {
+                match header {
+                    90 | 101 | 114 => self.parse_value(visitor),
+                    _ => Err(Error::InvalidTag),
+                }
+            }
+            PORT_STRUCT_NAME => {
+                match header {
+                    89 | 102 | 120 => self.parse_value(visitor),
+                    _ => Err(Error::InvalidTag),
+                }
+            }
+            EXPORT_FUN_STRUCT_NAME => {
+                match header {
+                    113 => self.parse_value(visitor),
+                    _ => Err(Error::InvalidTag),
+                }
+            }
+            CLOSURE_STRUCT_NAME => {
+                match header {
+                    112 => self.parse_value(visitor),
+                    _ => Err(Error::InvalidTag),
+                }
+            }
+            BITSTRING_STRUCT_NAME => {
+                self.header = None;
+                match header {
+                    77 | 109 => self.parse_bert_bitstring(header, visitor),
+                    _ => Err(Error::InvalidTag),
+                }
+            }
             _ => {
                 // Generic struct: LargeTuple(N+1, [Atom(name), field_pairs...])
                 self.header = None;
@@ -469,20 +919,18 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
                     104 => {
                         let arity = self.read_u8()? as usize;
                         // Read and skip the struct name atom
-                        let tag = self.read_u8()?;
-                        if tag != 100 { return Err(Error::InvalidTag); }
-                        let _struct_name = self.read_atom_value()?;
+                        let _struct_name = self.read_atom()?;
                         // Each field is SmallTuple(2, [Atom(field_name), value])
                         // We present as a map
                         visitor.visit_map(StructMapAccess::new(self, arity - 1))
                     }
                     105 => {
                         let arity = self.read_i32::<BigEndian>()? as usize;
-                        let tag = self.read_u8()?;
-                        if tag != 100 { return Err(Error::InvalidTag); }
-                        let _struct_name = self.read_atom_value()?;
+                        let _struct_name = self.read_atom()?;
                         visitor.visit_map(StructMapAccess::new(self, arity - 1))
                     }
+                    // Native map with atom keys for the struct fields
+                    116 => self.parse_map(visitor),
                     _ => Err(Error::InvalidTag),
                 }
             }
@@ -490,9 +938,20 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
     }
 
     fn deserialize_newtype_struct<V: Visitor<'de>>(
-        self, _name: &'static str, visitor: V
+        self, name: &'static str, visitor: V
     ) -> Result<V::Value> {
-        self.ensure_header()?;
+        use crate::wrappers::TERM_STRUCT_NAME;
+
+        let header = self.ensure_header()?;
+        if name == TERM_STRUCT_NAME {
+            // Atom cache references are resolved, so the term is passed
+            // as a standalone encoded term
+            self.header = None;
+            let term = read_term(&mut self.reader, header, &self.options.atom_cache_refs)?;
+            let mut binary = vec![];
+            term.write(&mut binary)?;
+            return visitor.visit_byte_buf(binary);
+        }
         self.parse_value(visitor)
     }
 
@@ -511,9 +970,19 @@ impl<'de, 'a, R: Read> de::Deserializer<'de> for &'a mut Deserializer<R> {
         self.deserialize_any(visitor)
     }
 
+    deserialize_integer!(deserialize_i8, visit_i8, i8);
+    deserialize_integer!(deserialize_i16, visit_i16, i16);
+    deserialize_integer!(deserialize_i32, visit_i32, i32);
+    deserialize_integer!(deserialize_i64, visit_i64, i64);
+    deserialize_integer!(deserialize_i128, visit_i128, i128);
+    deserialize_integer!(deserialize_u8, visit_u8, u8);
+    deserialize_integer!(deserialize_u16, visit_u16, u16);
+    deserialize_integer!(deserialize_u32, visit_u32, u32);
+    deserialize_integer!(deserialize_u64, visit_u64, u64);
+    deserialize_integer!(deserialize_u128, visit_u128, u128);
+
     serde::forward_to_deserialize_any! {
-        u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string
-        bytes byte_buf unit unit_struct
+        f32 f64 bytes byte_buf unit unit_struct
     }
 }
 
@@ -642,6 +1111,85 @@ impl<'de, 'a, R: Read> de::SeqAccess<'de> for PrependedSeqAccess<'a, R> {
     }
 }
 
+// Already decoded fields of the Erlang specific types (pids, ...)
+enum ExtensionValue {
+    Atom(String),
+    Bytes(Vec<u8>),
+    U8(u8),
+    U32(u32),
+    U64(u64),
+    Words(Vec<u32>),
+    Terms(Vec<Vec<u8>>),
+}
+
+struct ExtensionSeqAccess {
+    values: std::vec::IntoIter<ExtensionValue>,
+}
+
+impl ExtensionSeqAccess {
+    fn new(values: Vec<ExtensionValue>) -> Self {
+        ExtensionSeqAccess { values: values.into_iter() }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for ExtensionSeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.values.next() {
+            Some(ExtensionValue::Atom(value)) => {
+                seed.deserialize(de::value::StringDeserializer::new(value)).map(Some)
+            }
+            Some(ExtensionValue::Bytes(value)) => {
+                seed.deserialize(de::value::BytesDeserializer::new(&value)).map(Some)
+            }
+            Some(ExtensionValue::U8(value)) => {
+                seed.deserialize(de::value::U8Deserializer::new(value)).map(Some)
+            }
+            Some(ExtensionValue::U32(value)) => {
+                seed.deserialize(de::value::U32Deserializer::new(value)).map(Some)
+            }
+            Some(ExtensionValue::U64(value)) => {
+                seed.deserialize(de::value::U64Deserializer::new(value)).map(Some)
+            }
+            Some(ExtensionValue::Words(values)) => {
+                let words = de::value::SeqDeserializer::new(values.into_iter());
+                seed.deserialize(words).map(Some)
+            }
+            Some(ExtensionValue::Terms(values)) => {
+                let terms = RawTermsSeqAccess { values: values.into_iter() };
+                seed.deserialize(de::value::SeqAccessDeserializer::new(terms)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+// Raw encoded terms, presented as a seq of bytes
+struct RawTermsSeqAccess {
+    values: std::vec::IntoIter<Vec<u8>>,
+}
+
+impl<'de> de::SeqAccess<'de> for RawTermsSeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.values.next() {
+            Some(value) => seed.deserialize(de::value::BytesDeserializer::new(&value)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+
 struct DictMapAccess<'a, R: 'a + Read> {
     de: &'a mut Deserializer<R>,
     remaining: usize,
@@ -683,6 +1231,38 @@ impl<'de, 'a, R: Read> de::MapAccess<'de> for DictMapAccess<'a, R> {
     }
 }
 
+struct NativeMapAccess<'a, R: 'a + Read> {
+    de: &'a mut Deserializer<R>,
+    remaining: usize,
+}
+
+impl<'a, R: Read> NativeMapAccess<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, arity: usize) -> Self {
+        NativeMapAccess { de, remaining: arity }
+    }
+}
+
+impl<'de, 'a, R: Read> de::MapAccess<'de> for NativeMapAccess<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        // Keys and values follow each other without any wrapping
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
 struct StructMapAccess<'a, R: 'a + Read> {
     de: &'a mut Deserializer<R>,
     remaining: usize,
@@ -712,11 +1292,7 @@ impl<'de, 'a, R: Read> de::MapAccess<'de> for StructMapAccess<'a, R> {
             return Err(Error::InvalidTag);
         }
         // Read the field name atom
-        let atom_tag = self.de.read_u8()?;
-        if atom_tag != 100 {
-            return Err(Error::InvalidTag);
-        }
-        let field_name = self.de.read_atom_value()?;
+        let field_name = self.de.read_atom()?;
         seed.deserialize(de::value::StringDeserializer::new(field_name)).map(Some)
     }
 
@@ -731,7 +1307,18 @@ impl<'de, 'a, R: Read> de::MapAccess<'de> for StructMapAccess<'a, R> {
 
 /// Decodes a BERT value from a `std::io::Read`.
 #[inline]
-pub fn from_reader<T, R: Read>(mut reader: R) -> Result<T>
+pub fn from_reader<T, R: Read>(reader: R) -> Result<T>
+where
+    T: de::DeserializeOwned,
+{
+    from_reader_with_options(reader, DeserializerOptions::default())
+}
+
+/// Decodes a BERT value from a `std::io::Read` with the specified options.
+#[inline]
+pub fn from_reader_with_options<T, R: Read>(
+    mut reader: R, options: DeserializerOptions
+) -> Result<T>
 where
     T: de::DeserializeOwned,
 {
@@ -743,21 +1330,69 @@ where
         );
         Err(Error::Custom(message))
     } else {
-        let mut de = Deserializer::new(reader);
+        let tag = reader.read_u8()?;
+        if tag == 80 {
+            let data = read_compressed_term(reader)?;
+            let mut de = Deserializer::with_options(data.as_slice(), options);
+            let value = T::deserialize(&mut de)?;
+            de.end()?;
+            return Ok(value);
+        }
+
+        let mut de = Deserializer::with_options(reader, options);
+        de.header = Some(tag);
         let value = T::deserialize(&mut de)?;
         de.end()?;
         Ok(value)
     }
 }
 
+/// Inflate the zlib compressed term (after the COMPRESSED tag has been
+/// consumed), which is prefixed by the size of the uncompressed data. The
+/// zlib stream takes the rest of the input: it has to end right after the
+/// term, and nothing may follow it
+pub(crate) fn read_compressed_term<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let size = reader.read_u32::<BigEndian>()? as usize;
+    let mut compressed = Vec::new();
+    reader.read_to_end(&mut compressed)?;
+
+    let mut decoder = ZlibDecoder::new(compressed.as_slice());
+    let mut data = Vec::new();
+    (&mut decoder).take(size as u64).read_to_end(&mut data)?;
+    if data.len() != size {
+        return Err(Error::EndOfStream);
+    }
+    if decoder.read(&mut [0; 1])? != 0 || !decoder.into_inner().is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(data)
+}
+
 /// Decodes a BERT value from a `&[u8]` slice.
 #[inline]
 pub fn from_slice<T: de::DeserializeOwned>(v: &[u8]) -> Result<T> {
     from_reader(v)
 }
 
+/// Decodes a BERT value from a `&[u8]` slice with the specified options.
+#[inline]
+pub fn from_slice_with_options<T: de::DeserializeOwned>(
+    v: &[u8], options: DeserializerOptions
+) -> Result<T> {
+    from_reader_with_options(v, options)
+}
+
 /// Decode a BERT value from a binary stream (`&Vec<u8>`)
 #[inline]
 pub fn binary_to_term<T: de::DeserializeOwned>(value: &Vec<u8>) -> Result<T> {
     from_slice(value.as_slice())
 }
+
+/// Decode a BERT value from a binary stream (`&Vec<u8>`) with the
+/// specified options
+#[inline]
+pub fn binary_to_term_with_options<T: de::DeserializeOwned>(
+    value: &Vec<u8>, options: DeserializerOptions
+) -> Result<T> {
+    from_slice_with_options(value.as_slice(), options)
+}
@@ -0,0 +1,278 @@
+//! Distribution header support
+//!
+//! Messages between connected Erlang nodes start with a distribution header
+//! (DIST_HEADER or DIST_FRAG_HEADER) instead of the plain version number.
+//! The header lists the atoms of the control message and of the payload,
+//! which are referenced by index with ATOM_CACHE_REF. New atoms are stored
+//! in the atom cache of the connection, so the following messages can
+//! refer to them without sending the text again.
+use std::collections::HashMap;
+use std::io::Read;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use serde::{de, ser};
+
+use crate::deserializers::{Deserializer, DeserializerOptions};
+use crate::errors::{Error, Result};
+use crate::serializers::{Serializer, SerializerOptions};
+use crate::types::{ETF_VERSION, BertTag};
+
+
+/// Number of entries in the atom cache of a connection: 8 segments of 256
+pub const ATOM_CACHE_SIZE: usize = 2048;
+
+const NEW_CACHE_ENTRY_FLAG: u8 = 0x08;
+const SEGMENT_INDEX_MASK: u8 = 0x07;
+const LONG_ATOMS_FLAG: u8 = 0x01;
+
+
+/// Atom cache of a single connection. The same cache should be used for
+/// all messages sent (or received) over the connection
+#[derive(Debug, Clone)]
+pub struct AtomCache {
+    entries: Vec<Option<String>>,
+    indexes: HashMap<String, usize>,
+}
+
+
+impl Default for AtomCache {
+    fn default() -> Self {
+        AtomCache::new()
+    }
+}
+
+
+impl AtomCache {
+    pub fn new() -> AtomCache {
+        AtomCache {
+            entries: vec![None; ATOM_CACHE_SIZE],
+            indexes: HashMap::new(),
+        }
+    }
+
+    /// Returns the atom stored at the index
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index)?.as_deref()
+    }
+
+    /// Returns the index of the atom, if it's cached
+    pub fn index_of(&self, atom: &str) -> Option<usize> {
+        self.indexes.get(atom).copied()
+    }
+
+    /// Store the atom at the index, replacing the previous entry
+    pub fn insert(&mut self, index: usize, atom: &str) -> Result<()> {
+        let entry = self.entries.get_mut(index).ok_or_else(|| {
+            Error::Custom(format!("atom cache index {} is out of range", index))
+        })?;
+        if let Some(previous) = entry.take() {
+            self.indexes.remove(&previous);
+        }
+        *entry = Some(atom.to_string());
+        self.indexes.insert(atom.to_string(), index);
+        Ok(())
+    }
+}
+
+
+/// Position of a fragment of the message, sent in DIST_FRAG_HEADER
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistFragment {
+    pub sequence_id: u64,
+    pub fragment_id: u64,
+}
+
+
+/// Decoded DIST_HEADER or DIST_FRAG_HEADER
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistHeader {
+    /// Set only for DIST_FRAG_HEADER
+    pub fragment: Option<DistFragment>,
+    /// Atoms referenced by ATOM_CACHE_REF, in the order of the header
+    pub atoms: Vec<String>,
+}
+
+
+impl DistHeader {
+    /// Options for the `Deserializer`, which resolve ATOM_CACHE_REF
+    /// against the atoms of the header
+    pub fn deserializer_options(&self) -> DeserializerOptions {
+        DeserializerOptions {
+            atom_cache_refs: self.atoms.clone(),
+            ..DeserializerOptions::default()
+        }
+    }
+}
+
+
+/// Flags of the reference (or the header flags, when the index is equal
+/// to the number of references), stored as half-bytes
+#[inline]
+fn read_half_byte(flags: &[u8], index: usize) -> u8 {
+    (flags[index / 2] >> (4 * (index % 2))) & 0x0F
+}
+
+
+#[inline]
+fn write_half_byte(flags: &mut [u8], index: usize, value: u8) {
+    flags[index / 2] |= (value & 0x0F) << (4 * (index % 2));
+}
+
+
+/// Index of the atom cache entry for a new atom
+fn get_cache_index(atom: &str) -> usize {
+    // FNV-1a hash, so the same atom always goes to the same entry
+    let hash = atom.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    });
+    hash as usize % ATOM_CACHE_SIZE
+}
+
+
+/// Read DIST_HEADER or DIST_FRAG_HEADER (including the version number),
+/// storing new atoms in the cache. The reader is left at the control message
+pub fn read_dist_header<R: Read>(reader: &mut R, cache: &mut AtomCache) -> Result<DistHeader> {
+    if reader.read_u8()? != ETF_VERSION {
+        return Err(Error::InvalidVersionNumber);
+    }
+
+    let fragment = match reader.read_u8()? {
+        68 => None,
+        69 => {
+            let sequence_id = reader.read_u64::<BigEndian>()?;
+            let fragment_id = reader.read_u64::<BigEndian>()?;
+            Some(DistFragment { sequence_id, fragment_id })
+        }
+        _ => return Err(Error::InvalidTag),
+    };
+
+    let count = reader.read_u8()? as usize;
+    let mut atoms = Vec::with_capacity(count);
+    if count == 0 {
+        return Ok(DistHeader { fragment, atoms });
+    }
+
+    let mut flags = vec![0u8; count / 2 + 1];
+    reader.read_exact(&mut flags)?;
+    let long_atoms = read_half_byte(&flags, count) & LONG_ATOMS_FLAG != 0;
+
+    for position in 0..count {
+        let flag = read_half_byte(&flags, position);
+        let segment = (flag & SEGMENT_INDEX_MASK) as usize;
+        let index = segment * 256 + reader.read_u8()? as usize;
+
+        if flag & NEW_CACHE_ENTRY_FLAG != 0 {
+            let length = match long_atoms {
+                true => reader.read_u16::<BigEndian>()? as usize,
+                false => reader.read_u8()? as usize,
+            };
+            let mut text = vec![0u8; length];
+            reader.read_exact(&mut text)?;
+            let atom = String::from_utf8(text)?;
+            cache.insert(index, &atom)?;
+            atoms.push(atom);
+        } else {
+            let atom = cache.get(index).ok_or_else(|| {
+                Error::Custom(format!("atom cache entry {} is empty", index))
+            })?;
+            atoms.push(atom.to_string());
+        }
+    }
+
+    Ok(DistHeader { fragment, atoms })
+}
+
+
+/// Decode the distribution header, the control message and the optional
+/// payload. Fragmented messages should be reassembled first
+pub fn from_dist_slice<C, M>(
+    data: &[u8], cache: &mut AtomCache
+) -> Result<(DistHeader, C, Option<M>)>
+    where C: de::DeserializeOwned, M: de::DeserializeOwned
+{
+    let mut reader = data;
+    let header = read_dist_header(&mut reader, cache)?;
+    let options = header.deserializer_options();
+
+    let mut de = Deserializer::with_options(&mut reader, options.clone());
+    let control = C::deserialize(&mut de)?;
+    if reader.is_empty() {
+        return Ok((header, control, None));
+    }
+
+    let mut de = Deserializer::with_options(&mut reader, options);
+    let message = M::deserialize(&mut de)?;
+    de.end()?;
+    Ok((header, control, Some(message)))
+}
+
+
+/// Encode the control message and the optional payload with DIST_HEADER,
+/// where all atoms are written as references into the atom cache
+#[inline]
+pub fn to_dist_vec<C, M>(
+    control: &C, message: Option<&M>, cache: &mut AtomCache
+) -> Result<Vec<u8>>
+    where C: ser::Serialize, M: ser::Serialize
+{
+    to_dist_vec_with_options(control, message, cache, SerializerOptions::default())
+}
+
+
+/// Encode the control message and the optional payload with DIST_HEADER,
+/// using the specified encoding options
+pub fn to_dist_vec_with_options<C, M>(
+    control: &C, message: Option<&M>, cache: &mut AtomCache,
+    options: SerializerOptions
+) -> Result<Vec<u8>>
+    where C: ser::Serialize, M: ser::Serialize
+{
+    let mut ser = Serializer::with_options(Vec::new(), options);
+    ser.enable_atom_cache_refs();
+    control.serialize(&mut ser)?;
+    if let Some(message) = message {
+        message.serialize(&mut ser)?;
+    }
+    let atoms = ser.take_atom_cache_refs();
+    let body = ser.into_inner();
+
+    let count = atoms.len();
+    let mut binary = vec![ETF_VERSION, BertTag::DistHeader as u8, count as u8];
+    if count > 0 {
+        let long_atoms = atoms.iter().any(|atom| atom.len() > u8::MAX as usize);
+        let mut flags = vec![0u8; count / 2 + 1];
+        let mut refs = Vec::new();
+
+        for (position, atom) in atoms.iter().enumerate() {
+            let (index, is_new) = match cache.index_of(atom) {
+                Some(index) => (index, false),
+                None => {
+                    let index = get_cache_index(atom);
+                    cache.insert(index, atom)?;
+                    (index, true)
+                }
+            };
+
+            let mut flag = (index / 256) as u8;
+            refs.push((index % 256) as u8);
+            if is_new {
+                flag |= NEW_CACHE_ENTRY_FLAG;
+                match long_atoms {
+                    true => refs.write_u16::<BigEndian>(atom.len() as u16)?,
+                    false => refs.write_u8(atom.len() as u8)?,
+                }
+                refs.extend(atom.as_bytes());
+            }
+            write_half_byte(&mut flags, position, flag);
+        }
+        if long_atoms {
+            write_half_byte(&mut flags, count, LONG_ATOMS_FLAG);
+        }
+
+        binary.extend(flags);
+        binary.extend(refs);
+    }
+
+    binary.extend(body);
+    Ok(binary)
+}
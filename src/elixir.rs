@@ -0,0 +1,643 @@
+//! Elixir syntax for terms
+//!
+//! Terms are written the same way as `inspect` does: `:ok`, `"binary"`,
+//! `'charlist'`, `[key: value]` keyword lists, `%{key: value}` maps and
+//! `%Struct{}` for maps with the `__struct__` key. Module names are atoms
+//! with the `Elixir.` prefix, so `Enum` is the `'Elixir.Enum'` atom.
+use crate::errors::Result;
+use crate::parser::{Parser, insert_entry, list_characters, list_with_tail, string_term};
+use crate::printer::{flat_encoded, sorted_entries, write_float};
+use crate::term::{Term, decode_term};
+use crate::types::{BertBitString, BertExportFun};
+
+
+const MODULE_PREFIX: &str = "Elixir.";
+const STRUCT_KEY: &str = "__struct__";
+
+
+/// Check whether the name is written without quotes: `ok`, `valid?`
+fn is_identifier(name: &str) -> bool {
+    let name = name.strip_suffix(['?', '!']).unwrap_or(name);
+    let mut characters = name.chars();
+    match characters.next() {
+        Some(first) if first.is_alphabetic() || first == '_' => {
+            characters.all(|character| character.is_alphanumeric() || character == '_' || character == '@')
+        }
+        _ => false,
+    }
+}
+
+
+/// Module name without the `Elixir.` prefix, like `Foo.Bar`
+fn module_alias(name: &str) -> Option<&str> {
+    let alias = name.strip_prefix(MODULE_PREFIX)?;
+    let is_segment = |segment: &str| {
+        let mut characters = segment.chars();
+        characters.next().is_some_and(|first| first.is_ascii_uppercase())
+            && characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+    };
+    match alias.split('.').all(is_segment) {
+        true => Some(alias),
+        false => None,
+    }
+}
+
+
+/// Characters which are written in strings as is or as an escape sequence
+fn is_printable(character: char) -> bool {
+    !character.is_control()
+        || matches!(character, '\n' | '\r' | '\t' | '\u{b}' | '\u{8}' | '\u{c}' | '\u{1b}' | '\u{7}')
+}
+
+
+fn write_quoted(output: &mut String, text: &str, quote: char) {
+    output.push(quote);
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{b}' => output.push_str("\\v"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            '\u{1b}' => output.push_str("\\e"),
+            '\u{7}' => output.push_str("\\a"),
+            '\0' => output.push_str("\\0"),
+            '#' if characters.peek() == Some(&'{') => output.push_str("\\#"),
+            _ if character == quote => {
+                output.push('\\');
+                output.push(quote);
+            }
+            _ if character.is_control() => {
+                output.push_str(&format!("\\u{{{:X}}}", character as u32));
+            }
+            _ => output.push(character),
+        }
+    }
+    output.push(quote);
+}
+
+
+fn write_atom(output: &mut String, name: &str) {
+    match name {
+        "true" | "false" | "nil" => output.push_str(name),
+        _ => match module_alias(name) {
+            Some(alias) => output.push_str(alias),
+            None if is_identifier(name) => {
+                output.push(':');
+                output.push_str(name);
+            }
+            None => {
+                output.push(':');
+                write_quoted(output, name, '"');
+            }
+        },
+    }
+}
+
+
+/// Key of the keyword list or the map with atom keys: `key: `
+fn keyword_key(name: &str) -> String {
+    let mut output = String::new();
+    match is_identifier(name) {
+        true => output.push_str(name),
+        false => write_quoted(&mut output, name, '"'),
+    }
+    output.push_str(": ");
+    output
+}
+
+
+fn write_binary(output: &mut String, bytes: &[u8], bitstring: Option<&BertBitString>) {
+    if bitstring.is_none()
+        && let Ok(text) = std::str::from_utf8(bytes)
+        && text.chars().all(is_printable)
+    {
+        return write_quoted(output, text, '"');
+    }
+
+    let mut segments: Vec<String> = bytes.iter().map(u8::to_string).collect();
+    if let (Some(bitstring), Some(last)) = (bitstring, bytes.last()) {
+        let bits = bitstring.bits_in_last_byte;
+        segments.pop();
+        segments.push(format!("{}::size({})", last >> (8 - bits), bits));
+    }
+    output.push_str("<<");
+    output.push_str(&segments.join(", "));
+    output.push_str(">>");
+}
+
+
+/// Characters of the charlist, when they are all printable ASCII
+fn charlist(term: &Term) -> Option<String> {
+    let characters = match term {
+        Term::String(bytes) => bytes.iter().map(|byte| *byte as char).collect(),
+        Term::List(elements) => list_characters(elements)?,
+        _ => return None,
+    };
+    let printable = |character: &char| {
+        character.is_ascii() && (!character.is_ascii_control() || is_printable(*character))
+    };
+    match !characters.is_empty() && characters.iter().all(printable) {
+        true => Some(characters.into_iter().collect()),
+        false => None,
+    }
+}
+
+
+/// Names of the keys, if all of them are atoms
+fn atom_keys<'a, I: Iterator<Item = &'a Term>>(keys: I) -> Option<Vec<&'a str>> {
+    keys.map(|key| match key {
+            Term::Atom(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+
+/// Written parts of the tuple, the list or the map: the opening bracket,
+/// items with their prefixes (like keys), the list tail and the closing
+/// bracket
+struct Container {
+    open: String,
+    items: Vec<(String, Term)>,
+    tail: Option<Term>,
+    close: &'static str,
+}
+
+
+fn container(term: &Term) -> Option<Container> {
+    let container = match term {
+        Term::Tuple(elements) => Container {
+            open: "{".to_string(),
+            items: elements.iter().map(|element| (String::new(), element.clone())).collect(),
+            tail: None,
+            close: "}",
+        },
+        Term::String(_) | Term::List(_) | Term::ImproperList(..) if charlist(term).is_none() => {
+            let (elements, tail) = match term {
+                Term::String(bytes) => (bytes.iter().map(|byte| Term::Integer(*byte as i64)).collect(), None),
+                Term::List(elements) => (elements.clone(), None),
+                Term::ImproperList(elements, tail) => (elements.clone(), Some(*tail.clone())),
+                _ => (vec![], None),
+            };
+            // Lists of pairs with atom keys are keyword lists
+            let pairs: Option<Vec<(&Term, &Term)>> = elements.iter()
+                .map(|element| match element {
+                    Term::Tuple(pair) if pair.len() == 2 => Some((&pair[0], &pair[1])),
+                    _ => None,
+                })
+                .collect();
+            let keywords = pairs
+                .filter(|pairs| tail.is_none() && !pairs.is_empty())
+                .and_then(|pairs| {
+                    let names = atom_keys(pairs.iter().map(|(key, _)| *key))?;
+                    Some(names.into_iter()
+                        .zip(pairs)
+                        .map(|(name, (_, value))| (keyword_key(name), value.clone()))
+                        .collect())
+                });
+            Container {
+                open: "[".to_string(),
+                items: keywords.unwrap_or_else(|| {
+                    elements.iter().map(|element| (String::new(), element.clone())).collect()
+                }),
+                tail,
+                close: "]",
+            }
+        }
+        Term::Map(entries) => {
+            let mut entries = sorted_entries(entries);
+            let mut open = "%{".to_string();
+            let struct_name = entries.iter()
+                .find_map(|(key, value)| match (key, value) {
+                    (Term::Atom(key), Term::Atom(name)) if key == STRUCT_KEY => module_alias(name),
+                    _ => None,
+                });
+            if let Some(name) = struct_name {
+                open = format!("%{}{{", name);
+                entries.retain(|(key, _)| *key != Term::Atom(STRUCT_KEY.to_string()));
+            }
+
+            let items = match atom_keys(entries.iter().map(|(key, _)| key)) {
+                Some(names) => names.into_iter()
+                    .zip(entries.iter())
+                    .map(|(name, (_, value))| (keyword_key(name), value.clone()))
+                    .collect(),
+                None => entries.iter()
+                    .map(|(key, value)| (format!("{} => ", flat(key)), value.clone()))
+                    .collect(),
+            };
+            Container { open, items, tail: None, close: "}" }
+        }
+        _ => return None,
+    };
+    Some(container)
+}
+
+
+fn flat(term: &Term) -> String {
+    let mut output = String::new();
+    write_elixir(&mut output, term);
+    output
+}
+
+
+/// Module and function of the fun: `&Enum.map/2` or `&:lists.reverse/1`
+fn write_fun(output: &mut String, fun: &BertExportFun) {
+    output.push('&');
+    write_atom(output, &fun.module);
+    output.push('.');
+    match is_identifier(&fun.function) {
+        true => output.push_str(&fun.function),
+        false => write_quoted(output, &fun.function, '"'),
+    }
+    output.push_str(&format!("/{}", fun.arity));
+}
+
+
+/// Write the term on a single line in the Elixir syntax
+pub(crate) fn write_elixir(output: &mut String, term: &Term) {
+    if let Some(container) = container(term) {
+        output.push_str(&container.open);
+        for (index, (prefix, value)) in container.items.iter().enumerate() {
+            if index > 0 {
+                output.push_str(", ");
+            }
+            output.push_str(prefix);
+            write_elixir(output, value);
+        }
+        if let Some(tail) = &container.tail {
+            output.push_str(" | ");
+            write_elixir(output, tail);
+        }
+        output.push_str(container.close);
+        return;
+    }
+
+    match term {
+        Term::Atom(name) => write_atom(output, name),
+        Term::Integer(value) => output.push_str(&value.to_string()),
+        Term::BigInteger(value) => output.push_str(&value.to_string()),
+        Term::Float(value) => write_float(output, *value),
+        Term::Binary(bytes) => write_binary(output, bytes, None),
+        Term::BitString(bitstring) => match bitstring.bits_in_last_byte {
+            1..=7 => write_binary(output, &bitstring.bytes, Some(bitstring)),
+            _ => write_binary(output, &bitstring.bytes, None),
+        },
+        Term::String(_) | Term::List(_) => {
+            write_quoted(output, &charlist(term).unwrap_or_default(), '\'')
+        }
+        Term::Pid(pid) => output.push_str(&format!("#PID<0.{}.{}>", pid.id, pid.serial)),
+        Term::Reference(reference) => {
+            let words: Vec<String> = reference.id.iter().rev().map(u32::to_string).collect();
+            output.push_str(&format!("#Reference<0.{}>", words.join(".")));
+        }
+        Term::Port(port) => output.push_str(&format!("#Port<0.{}>", port.id)),
+        Term::ExportFun(fun) => write_fun(output, fun),
+        Term::Closure(closure) => {
+            let module = match decode_term(&closure.module) {
+                Ok(module) => flat(&module),
+                Err(_) => "?".to_string(),
+            };
+            output.push_str(&format!(
+                "#Function<{}.{}/{} in {}>",
+                flat_encoded(&closure.old_index),
+                flat_encoded(&closure.old_uniq),
+                closure.arity,
+                module,
+            ));
+        }
+        Term::ImproperList(..) | Term::Tuple(_) | Term::Map(_) => {}
+    }
+}
+
+
+/// Write the term in the Elixir syntax. Containers which don't fit into
+/// the width put each item on its own line, indented by two spaces
+pub(crate) fn write_elixir_pretty(output: &mut String, term: &Term, indent: usize, width: usize, suffix: usize) {
+    let column = output.chars().rev().take_while(|character| *character != '\n').count();
+    let single_line = flat(term);
+    let container = match container(term) {
+        Some(container) if column + single_line.chars().count() + suffix > width => container,
+        _ => return output.push_str(&single_line),
+    };
+
+    let tail = container.tail.as_ref().map(flat);
+    output.push_str(&container.open);
+    for (index, (prefix, value)) in container.items.iter().enumerate() {
+        output.push('\n');
+        output.push_str(&" ".repeat(indent + 2));
+        output.push_str(prefix);
+        let is_last = index + 1 == container.items.len();
+        let item_suffix = match (is_last, &tail) {
+            (false, _) => 1,
+            (true, Some(tail)) => tail.chars().count() + 3,
+            (true, None) => 0,
+        };
+        write_elixir_pretty(output, value, indent + 2, width, item_suffix);
+        if !is_last {
+            output.push(',');
+        }
+    }
+    if let Some(tail) = tail {
+        output.push_str(" | ");
+        output.push_str(&tail);
+    }
+    output.push('\n');
+    output.push_str(&" ".repeat(indent));
+    output.push_str(container.close);
+}
+
+
+impl Parser {
+    /// Parse the term in the Elixir syntax
+    pub(crate) fn parse_elixir_term(&mut self) -> Result<Term> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.position += 1;
+                let (elements, _) = self.parse_elements('}', false)?;
+                Ok(Term::Tuple(elements))
+            }
+            Some('[') => {
+                self.position += 1;
+                self.parse_elixir_list()
+            }
+            Some('%') => {
+                self.position += 1;
+                let mut entries = vec![];
+                if self.peek() != Some('{') {
+                    let name = self.parse_alias()?;
+                    entries.push((Term::Atom(STRUCT_KEY.to_string()), Term::Atom(name)));
+                }
+                self.expect("{")?;
+                self.parse_elixir_map(entries)
+            }
+            Some('<') => {
+                self.expect("<<")?;
+                self.parse_elixir_binary()
+            }
+            Some('"') => {
+                self.position += 1;
+                let text: String = self.parse_quoted('"')?.into_iter().collect();
+                Ok(Term::Binary(text.into_bytes()))
+            }
+            Some('\'') => {
+                self.position += 1;
+                Ok(string_term(self.parse_quoted('\'')?))
+            }
+            Some(':') => {
+                self.position += 1;
+                Ok(Term::Atom(self.parse_atom_name()?))
+            }
+            Some('?') => {
+                self.position += 1;
+                let character = match self.next()? {
+                    '\\' => self.parse_escape()?,
+                    character => character,
+                };
+                Ok(Term::Integer(character as i64))
+            }
+            Some('&') => {
+                self.position += 1;
+                self.parse_capture()
+            }
+            Some('-') | Some('+') => {
+                let negative = self.next()? == '-';
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(character) if character.is_ascii_digit() => self.parse_number(negative),
+                    _ => Err(self.error("expected a number after the sign")),
+                }
+            }
+            Some(character) if character.is_ascii_digit() => self.parse_number(false),
+            Some(character) if character.is_uppercase() => Ok(Term::Atom(self.parse_alias()?)),
+            Some(character) if character.is_alphabetic() || character == '_' => {
+                let name = self.parse_identifier();
+                match name.as_str() {
+                    "true" | "false" | "nil" => Ok(Term::Atom(name)),
+                    _ => Err(self.error(&format!("variable '{}' can't be a term", name))),
+                }
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of text")),
+        }
+    }
+
+    /// Name with the optional `?` or `!` at the end
+    fn parse_identifier(&mut self) -> String {
+        let mut name = self.take_while(|character| {
+            character.is_alphanumeric() || character == '_' || character == '@'
+        });
+        if let Some(character @ ('?' | '!')) = self.peek() {
+            self.position += 1;
+            name.push(character);
+        }
+        name
+    }
+
+    /// Atom after the colon: `:ok` or `:"quoted atom"`
+    fn parse_atom_name(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.position += 1;
+                Ok(self.parse_quoted(quote)?.into_iter().collect())
+            }
+            _ => match self.parse_identifier() {
+                name if name.is_empty() => Err(self.error("expected an atom")),
+                name => Ok(name),
+            },
+        }
+    }
+
+    /// Module name, like `Foo.Bar`, as the `'Elixir.Foo.Bar'` atom
+    fn parse_alias(&mut self) -> Result<String> {
+        let mut name = MODULE_PREFIX.to_string();
+        loop {
+            match self.peek() {
+                Some(character) if character.is_ascii_uppercase() => {
+                    name.push_str(&self.take_while(|character| {
+                        character.is_ascii_alphanumeric() || character == '_'
+                    }));
+                }
+                _ => return Err(self.error("expected a module name")),
+            }
+            // The dot is followed by a function name in captures
+            match (self.peek(), self.peek_at(1)) {
+                (Some('.'), Some(character)) if character.is_ascii_uppercase() => {
+                    self.position += 1;
+                    name.push('.');
+                }
+                _ => return Ok(name),
+            }
+        }
+    }
+
+    /// Key of the keyword list or the map: `key: ` or `"quoted key": `.
+    /// Nothing is consumed when there is no key
+    fn parse_keyword_key(&mut self) -> Result<Option<String>> {
+        self.skip_whitespace();
+        let start = self.position;
+        let name = match self.peek() {
+            Some('"') => {
+                self.position += 1;
+                self.parse_quoted('"')?.into_iter().collect()
+            }
+            Some(character) if character.is_alphabetic() || character == '_' => self.parse_identifier(),
+            _ => return Ok(None),
+        };
+        match (self.peek(), self.peek_at(1)) {
+            (Some(':'), Some(character)) if character.is_whitespace() => {
+                self.position += 1;
+                Ok(Some(name))
+            }
+            _ => {
+                self.position = start;
+                Ok(None)
+            }
+        }
+    }
+
+    fn parse_elixir_list(&mut self) -> Result<Term> {
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.position += 1;
+            return Ok(Term::List(elements));
+        }
+
+        loop {
+            match self.parse_keyword_key()? {
+                Some(key) => {
+                    let value = self.parse_term()?;
+                    elements.push(Term::Tuple(vec![Term::Atom(key), value]));
+                }
+                None => elements.push(self.parse_term()?),
+            }
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(Term::List(elements)),
+                '|' => {
+                    let tail = self.parse_term()?;
+                    self.expect("]")?;
+                    return Ok(list_with_tail(elements, tail));
+                }
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',', '|' or ']'"));
+                }
+            }
+        }
+    }
+
+    fn parse_elixir_map(&mut self, mut entries: Vec<(Term, Term)>) -> Result<Term> {
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Term::Map(entries));
+        }
+
+        loop {
+            let key = match self.parse_keyword_key()? {
+                Some(key) => Term::Atom(key),
+                None => {
+                    let key = self.parse_term()?;
+                    self.expect("=>")?;
+                    key
+                }
+            };
+            let value = self.parse_term()?;
+            insert_entry(&mut entries, key, value);
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Term::Map(entries)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+    }
+
+    /// Binary segments: bytes, truncated to 8 bits, and strings, which are
+    /// encoded as UTF-8. The last byte can have a size in bits, like
+    /// `<<1, 15::size(4)>>`
+    fn parse_elixir_binary(&mut self) -> Result<Term> {
+        let mut bytes = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('>') {
+            self.expect(">>")?;
+            return Ok(Term::Binary(bytes));
+        }
+
+        loop {
+            match self.parse_term()? {
+                Term::Integer(value) => {
+                    self.skip_whitespace();
+                    if self.peek() == Some(':') {
+                        self.expect("::")?;
+                        self.skip_whitespace();
+                        let size = match self.peek() {
+                            Some('s') => {
+                                self.expect("size(")?;
+                                let size = self.parse_term()?;
+                                self.skip_whitespace();
+                                self.expect(")")?;
+                                size
+                            }
+                            _ => self.parse_term()?,
+                        };
+                        return self.parse_bits_segment(bytes, value, size);
+                    }
+                    bytes.push(value as u8);
+                }
+                Term::Binary(segment) => bytes.extend(segment),
+                _ => return Err(self.error("expected a byte or a string in the binary")),
+            }
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '>' if self.peek() == Some('>') => {
+                    self.position += 1;
+                    return Ok(Term::Binary(bytes));
+                }
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',' or '>>'"));
+                }
+            }
+        }
+    }
+
+    /// External fun after the `&`: `&Module.function/arity`
+    fn parse_capture(&mut self) -> Result<Term> {
+        let module = match self.peek() {
+            Some(':') => {
+                self.position += 1;
+                self.parse_atom_name()?
+            }
+            _ => self.parse_alias()?,
+        };
+        self.expect(".")?;
+        let function = match self.peek() {
+            Some('"') => {
+                self.position += 1;
+                self.parse_quoted('"')?.into_iter().collect()
+            }
+            _ => self.parse_identifier(),
+        };
+        self.expect("/")?;
+        self.skip_whitespace();
+        let arity = self.parse_digits(10)?.parse::<u8>()
+            .map_err(|_| self.error("arity must be between 0 and 255"))?;
+        Ok(Term::ExportFun(BertExportFun::new(&module, &function, arity)))
+    }
+}
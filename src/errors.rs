@@ -26,10 +26,28 @@ pub enum Error {
     InvalidTag,
     /// Passed type of value is not supported by BERT.
     UnsupportedType,
+    /// Decoded integer doesn't fit into the requested integer type.
+    IntegerOverflow(&'static str),
+    /// Bitstring was passed where bytes are expected, but decoding
+    /// bitstrings as bytes is not enabled.
+    UnexpectedBitstring,
+    /// Atom is longer than 255 characters, which Erlang doesn't accept.
+    AtomTooLong(String),
+    /// Float is NaN or infinite, which FLOAT_EXT can't represent.
+    NonFiniteFloat(f64),
+    /// Number of bits in the last byte of a bitstring is out of range.
+    InvalidBitCount(u8),
+    /// List with a tail other than nil was passed where a proper list
+    /// is expected.
+    ImproperList,
     /// Version number has not specified or invalid.
     InvalidVersionNumber,
     /// The data source contains trailing bytes after all values were read.
     TrailingBytes,
+    /// Term is nested deeper than the decoder allows.
+    NestingTooDeep,
+    /// Erlang term text is malformed at the line and column.
+    Syntax { line: usize, column: usize, message: String },
 }
 
 
@@ -45,10 +63,32 @@ impl fmt::Display for Error {
                 write!(f, "Invalid float '{}'.", value)
             },
             Error::UnsupportedType => f.write_str("type is not supported by BERT"),
+            Error::IntegerOverflow(type_name) => {
+                write!(f, "integer value is out of range for {}", type_name)
+            },
+            Error::UnexpectedBitstring => {
+                f.write_str("bitstring is not accepted as a binary value")
+            },
+            Error::AtomTooLong(ref name) => {
+                write!(f, "atom '{}' is longer than 255 characters", name)
+            },
+            Error::NonFiniteFloat(value) => {
+                write!(f, "float {} can't be written as FLOAT_EXT", value)
+            },
+            Error::InvalidBitCount(bits) => {
+                write!(f, "bitstring can't have {} bits in the last byte", bits)
+            },
+            Error::ImproperList => {
+                f.write_str("expected a proper list, but the list tail is not nil")
+            },
             Error::InvalidVersionNumber => {
                 f.write_str("version number has not specified or invalid")
             }
             Error::TrailingBytes => f.write_str("unexpected trailing bytes"),
+            Error::NestingTooDeep => f.write_str("term is nested too deeply"),
+            Error::Syntax { line, column, ref message } => {
+                write!(f, "syntax error at line {}, column {}: {}", line, column, message)
+            }
         }
     }
 }
@@ -1,29 +1,56 @@
 #[macro_use]
 mod enum_macro;
+mod term_macro;
 
 pub mod deserializers;
+pub mod dist;
+mod elixir;
 pub mod errors;
+pub mod order;
+pub mod parser;
+mod printer;
 pub mod serializers;
+pub mod term;
+pub mod term_ref;
 pub mod types;
 pub mod wrappers;
 pub mod utils;
 
 pub use deserializers::{
-    Deserializer,
-    binary_to_term, from_slice, from_reader
+    Deserializer, DeserializerOptions,
+    binary_to_term, binary_to_term_with_options,
+    from_slice, from_slice_with_options, from_reader, from_reader_with_options
+};
+pub use dist::{
+    AtomCache, DistHeader, DistFragment,
+    read_dist_header, from_dist_slice, to_dist_vec, to_dist_vec_with_options
 };
 pub use errors::{Error, Result};
+pub use order::compare_terms;
+pub use parser::{
+    parse_term, parse_term_with_syntax, consult, consult_with_syntax,
+    text_to_binary, text_to_binary_with_syntax
+};
+pub use printer::{DEFAULT_LINE_WIDTH, Syntax};
 pub use serializers::{
-    Serializer,
-    term_to_binary, to_vec, to_writer
+    Serializer, SerializerOptions, MapEncoding, MinorVersion,
+    term_to_binary, term_to_binary_with_options,
+    term_to_binary_compressed, term_to_binary_compressed_with_options,
+    to_vec, to_vec_with_options, to_writer, to_writer_with_options
 };
+pub use term::Term;
+#[doc(hidden)]
+pub use term_macro::BinarySegment;
+pub use term_ref::{TermRef, Elements, Entries};
 pub use types::{
-    BERT_LABEL, ETF_VERSION,
-    BertTag, BertBigInteger, BertTime, BertRegex,
+    BERT_LABEL, ETF_VERSION, MAX_ATOM_LENGTH,
+    BertTag, BertBigInteger, BertTime, BertRegex, BertPid, BertReference, BertPort, BertBitString,
+    BertExportFun, BertClosure, ImproperList, PidEncoding,
     TimeStruct, RegexStruct, RegexOption,
 };
 pub use utils::{
-    merge_terms, str_to_binary,
-    get_atom, get_nil, get_bert_nil, get_bert_atom, get_empty_tuple,
-    get_small_tuple
+    merge_terms, str_to_binary, get_string,
+    get_atom, try_get_atom, get_exact_atom, get_utf8_atom, get_small_atom, get_small_utf8_atom,
+    get_legacy_float,
+    get_integer, get_bignum, get_nil, get_bert_nil, get_bert_atom, get_empty_tuple, get_small_tuple
 };
@@ -0,0 +1,275 @@
+//! Erlang term order
+//!
+//! Terms of different types are ordered as
+//! `number < atom < reference < fun < port < pid < tuple < map < nil < list
+//! < bit string`. Terms of the same type are compared by their values: tuples
+//! by the size first, maps by the size, then by the keys and then by the
+//! values, lists and binaries element by element.
+use std::cmp::Ordering;
+
+use byteorder::ReadBytesExt;
+use num::bigint::BigInt;
+use num::ToPrimitive;
+
+use crate::errors::Result;
+use crate::term::{Term, decode_term, read_term};
+
+
+const NIL_RANK: u8 = 8;
+const LIST_RANK: u8 = 9;
+
+
+/// List without elements is written as its tail only
+fn skip_empty_lists(mut term: &Term) -> &Term {
+    while let Term::ImproperList(elements, tail) = term {
+        if !elements.is_empty() {
+            break;
+        }
+        term = tail;
+    }
+    term
+}
+
+
+/// Position of the term type in the term order
+fn rank(term: &Term) -> u8 {
+    match term {
+        Term::Integer(_) | Term::BigInteger(_) | Term::Float(_) => 0,
+        Term::Atom(_) => 1,
+        Term::Reference(_) => 2,
+        Term::ExportFun(_) | Term::Closure(_) => 3,
+        Term::Port(_) => 4,
+        Term::Pid(_) => 5,
+        Term::Tuple(_) => 6,
+        Term::Map(_) => 7,
+        Term::List(elements) if elements.is_empty() => NIL_RANK,
+        Term::String(bytes) if bytes.is_empty() => NIL_RANK,
+        Term::ImproperList(elements, tail) if elements.is_empty() => rank(tail),
+        Term::List(_) | Term::String(_) | Term::ImproperList(..) => LIST_RANK,
+        Term::Binary(_) | Term::BitString(_) => 10,
+    }
+}
+
+
+/// List element, bytes of STRING_EXT are compared as integers
+#[derive(Clone, Copy)]
+enum Element<'a> {
+    Term(&'a Term),
+    Byte(u8),
+}
+
+
+/// Elements of the list (including the elements of list tails) and the
+/// tail other than nil
+fn flatten_list(mut term: &Term) -> (Vec<Element<'_>>, Option<&Term>) {
+    let mut elements = vec![];
+    loop {
+        match term {
+            Term::List(list) => {
+                elements.extend(list.iter().map(Element::Term));
+                return (elements, None);
+            }
+            Term::String(bytes) => {
+                elements.extend(bytes.iter().map(|&byte| Element::Byte(byte)));
+                return (elements, None);
+            }
+            Term::ImproperList(list, tail) => {
+                elements.extend(list.iter().map(Element::Term));
+                term = tail;
+            }
+            tail => return (elements, Some(tail)),
+        }
+    }
+}
+
+
+fn compare_elements(left: Element, right: Element, exact: bool) -> Ordering {
+    match (left, right) {
+        (Element::Byte(left), Element::Byte(right)) => left.cmp(&right),
+        (Element::Term(left), Element::Byte(right)) => {
+            compare(left, &Term::Integer(right as i64), exact)
+        }
+        (Element::Byte(left), Element::Term(right)) => {
+            compare(&Term::Integer(left as i64), right, exact)
+        }
+        (Element::Term(left), Element::Term(right)) => compare(left, right, exact),
+    }
+}
+
+
+fn compare_lists(left: &Term, right: &Term, exact: bool) -> Ordering {
+    let (left_elements, left_tail) = flatten_list(left);
+    let (right_elements, right_tail) = flatten_list(right);
+
+    for (left, right) in left_elements.iter().zip(right_elements.iter()) {
+        let ordering = compare_elements(*left, *right, exact);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    // Once one of the lists runs out of elements, its tail is compared
+    // with the rest of the other list
+    let nil = Term::List(vec![]);
+    match left_elements.len().cmp(&right_elements.len()) {
+        Ordering::Equal => {
+            compare(left_tail.unwrap_or(&nil), right_tail.unwrap_or(&nil), exact)
+        }
+        Ordering::Less => rank(left_tail.unwrap_or(&nil)).cmp(&LIST_RANK),
+        Ordering::Greater => LIST_RANK.cmp(&rank(right_tail.unwrap_or(&nil))),
+    }
+}
+
+
+/// Compare numbers by their values. In the exact order, which is used
+/// for map keys, integers are less than floats
+fn compare_numbers(left: &Term, right: &Term, exact: bool) -> Ordering {
+    let integer = |term: &Term| match term {
+        Term::Integer(value) => Some(BigInt::from(*value)),
+        Term::BigInteger(value) => Some(value.clone()),
+        _ => None,
+    };
+    let float = |term: &Term| integer(term).and_then(|value| value.to_f64()).unwrap_or(f64::NAN);
+
+    match (left, right) {
+        (Term::Float(left), Term::Float(right)) => left.total_cmp(right),
+        (Term::Float(_), _) if exact => Ordering::Greater,
+        (_, Term::Float(_)) if exact => Ordering::Less,
+        (Term::Float(left), right) => left.partial_cmp(&float(right)).unwrap_or(Ordering::Equal),
+        (left, Term::Float(right)) => float(left).partial_cmp(right).unwrap_or(Ordering::Equal),
+        (left, right) => integer(left).cmp(&integer(right)),
+    }
+}
+
+
+fn compare_bits(left: &Term, right: &Term) -> Ordering {
+    let bits = |term: &Term| match term {
+        Term::BitString(bitstring) => {
+            // Bit counts which Erlang doesn't accept are taken as whole bytes
+            let unused = 8usize.saturating_sub(bitstring.bits_in_last_byte as usize) % 8;
+            let mut bytes = bitstring.bytes.clone();
+            if let Some(last) = bytes.last_mut() {
+                *last &= 0xFF << unused;
+            }
+            (bytes, (bitstring.bytes.len() * 8).saturating_sub(unused))
+        }
+        Term::Binary(bytes) => (bytes.clone(), bytes.len() * 8),
+        _ => (vec![], 0),
+    };
+
+    // Bytes are compared with the unused bits set to zero, then the
+    // shorter bit string goes first
+    let (left_bytes, left_length) = bits(left);
+    let (right_bytes, right_length) = bits(right);
+    left_bytes.cmp(&right_bytes).then_with(|| left_length.cmp(&right_length))
+}
+
+
+/// Compare the encoded funs, as they have no meaningful order
+fn compare_funs(left: &Term, right: &Term) -> Ordering {
+    let encode = |term: &Term| {
+        let mut binary = vec![];
+        term.write(&mut binary).map(|_| binary).unwrap_or_default()
+    };
+    encode(left).cmp(&encode(right))
+}
+
+
+/// Compare the terms pairwise, stopping at the first difference
+fn compare_all<'a, I>(pairs: I, exact: bool) -> Ordering
+    where I: Iterator<Item = (&'a Term, &'a Term)>
+{
+    pairs
+        .map(|(left, right)| compare(left, right, exact))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+
+/// Map entries sorted by their keys
+fn sorted_entries(entries: &[(Term, Term)]) -> Vec<&(Term, Term)> {
+    let mut sorted: Vec<_> = entries.iter().collect();
+    sorted.sort_by(|left, right| compare(&left.0, &right.0, true));
+    sorted
+}
+
+
+/// Compare the terms. In the exact order, which is used for map keys,
+/// integers are less than floats
+pub(crate) fn compare(left: &Term, right: &Term, exact: bool) -> Ordering {
+    let left = skip_empty_lists(left);
+    let right = skip_empty_lists(right);
+
+    let ordering = rank(left).cmp(&rank(right));
+    if ordering != Ordering::Equal {
+        return ordering;
+    }
+
+    match (left, right) {
+        (Term::Atom(left), Term::Atom(right)) => left.cmp(right),
+        (Term::Reference(left), Term::Reference(right)) => {
+            // Nodes are compared by the name and then by the creation. The
+            // last id word is the most significant one
+            left.node.cmp(&right.node)
+                .then_with(|| left.creation.cmp(&right.creation))
+                .then_with(|| left.id.len().cmp(&right.id.len()))
+                .then_with(|| left.id.iter().rev().cmp(right.id.iter().rev()))
+        }
+        (Term::Port(left), Term::Port(right)) => {
+            left.node.cmp(&right.node)
+                .then_with(|| left.creation.cmp(&right.creation))
+                .then_with(|| left.id.cmp(&right.id))
+        }
+        (Term::Pid(left), Term::Pid(right)) => {
+            left.node.cmp(&right.node)
+                .then_with(|| left.creation.cmp(&right.creation))
+                .then_with(|| left.serial.cmp(&right.serial))
+                .then_with(|| left.id.cmp(&right.id))
+        }
+        (Term::Tuple(left), Term::Tuple(right)) => {
+            left.len().cmp(&right.len())
+                .then_with(|| compare_all(left.iter().zip(right.iter()), exact))
+        }
+        (Term::Map(left), Term::Map(right)) => {
+            let left = sorted_entries(left);
+            let right = sorted_entries(right);
+            let pairs = || left.iter().zip(right.iter());
+            left.len().cmp(&right.len())
+                .then_with(|| compare_all(pairs().map(|(left, right)| (&left.0, &right.0)), true))
+                .then_with(|| compare_all(pairs().map(|(left, right)| (&left.1, &right.1)), exact))
+        }
+        _ => match rank(left) {
+            0 => compare_numbers(left, right, exact),
+            3 => compare_funs(left, right),
+            NIL_RANK => Ordering::Equal,
+            LIST_RANK => compare_lists(left, right, exact),
+            _ => compare_bits(left, right),
+        },
+    }
+}
+
+
+/// Compare two encoded terms (without the version number) in the Erlang
+/// term order
+pub fn compare_terms(left: &[u8], right: &[u8]) -> Result<Ordering> {
+    let left = decode_term(left)?;
+    let right = decode_term(right)?;
+    Ok(compare(&left, &right, false))
+}
+
+
+/// Sort the encoded map entries by their keys, in the order which Erlang
+/// uses for map keys. `atoms` resolve ATOM_CACHE_REF in the keys
+pub(crate) fn sort_map_entries(
+    entries: Vec<(Vec<u8>, Vec<u8>)>, atoms: &[String]
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    let mut decoded = entries.into_iter()
+        .map(|entry| {
+            let mut reader = entry.0.as_slice();
+            let tag = reader.read_u8()?;
+            Ok((read_term(&mut reader, tag, atoms)?, entry))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    decoded.sort_by(|left, right| compare(&left.0, &right.0, true));
+    Ok(decoded.into_iter().map(|(_, entry)| entry).collect())
+}
@@ -0,0 +1,622 @@
+//! Parser for the Erlang term syntax
+//!
+//! Reads literal terms in the format of `file:consult`: a sequence of terms,
+//! each one terminated with a dot, with `%` comments. Strings are lists of
+//! characters, as in Erlang, so the parsed terms are encoded the same way
+//! as `term_to_binary` encodes them after `file:consult`, except that map
+//! entries are kept in the order they were written. With `Syntax::Elixir`
+//! the terms are read in the Elixir syntax instead, one after another
+//! without the dots, with `#` comments.
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+use num::bigint::BigInt;
+use num::Num;
+
+use crate::errors::{Error, Result};
+use crate::order::compare;
+use crate::printer::{Syntax, RESERVED_WORDS, is_lowercase, is_name_character};
+use crate::term::Term;
+use crate::types::{BertBitString, BertExportFun};
+
+
+impl FromStr for Term {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Term> {
+        parse_term(text)
+    }
+}
+
+
+/// Parse a single term. The terminating dot is optional
+pub fn parse_term(text: &str) -> Result<Term> {
+    parse_term_with_syntax(text, Syntax::Erlang)
+}
+
+
+/// Parse a single term in the syntax
+pub fn parse_term_with_syntax(text: &str, syntax: Syntax) -> Result<Term> {
+    let mut parser = Parser::new(text, syntax);
+    let term = parser.parse_term()?;
+    parser.skip_whitespace();
+    if syntax == Syntax::Erlang && parser.peek() == Some('.') {
+        parser.parse_dot()?;
+    }
+    match parser.is_at_end() {
+        true => Ok(term),
+        false => Err(parser.error("unexpected text after the term")),
+    }
+}
+
+
+/// Parse the dot-terminated terms, like `file:consult` does
+pub fn consult(text: &str) -> Result<Vec<Term>> {
+    consult_with_syntax(text, Syntax::Erlang)
+}
+
+
+/// Parse the sequence of terms in the syntax. Elixir terms are not
+/// terminated with dots
+pub fn consult_with_syntax(text: &str, syntax: Syntax) -> Result<Vec<Term>> {
+    let mut parser = Parser::new(text, syntax);
+    let mut terms = vec![];
+    while !parser.is_at_end() {
+        terms.push(parser.parse_term()?);
+        if syntax == Syntax::Erlang {
+            parser.parse_dot()?;
+        }
+    }
+    Ok(terms)
+}
+
+
+/// Parse a single term and encode it with the version number, ready to be
+/// passed to `from_slice`
+pub fn text_to_binary(text: &str) -> Result<Vec<u8>> {
+    text_to_binary_with_syntax(text, Syntax::Erlang)
+}
+
+
+/// Parse a single term in the syntax and encode it with the version number
+pub fn text_to_binary_with_syntax(text: &str, syntax: Syntax) -> Result<Vec<u8>> {
+    parse_term_with_syntax(text, syntax)?.to_bytes()
+}
+
+
+pub(crate) struct Parser {
+    characters: Vec<char>,
+    pub(crate) position: usize,
+    pub(crate) syntax: Syntax,
+}
+
+
+impl Parser {
+    fn new(text: &str, syntax: Syntax) -> Parser {
+        Parser {
+            characters: text.chars().collect(),
+            position: 0,
+            syntax,
+        }
+    }
+
+    pub(crate) fn error(&self, message: &str) -> Error {
+        let consumed = &self.characters[..self.position.min(self.characters.len())];
+        let line = consumed.iter().filter(|character| **character == '\n').count() + 1;
+        let column = consumed.iter().rev().take_while(|character| **character != '\n').count() + 1;
+        Error::Syntax { line, column, message: message.to_string() }
+    }
+
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    pub(crate) fn peek_at(&self, offset: usize) -> Option<char> {
+        self.characters.get(self.position + offset).copied()
+    }
+
+    pub(crate) fn next(&mut self) -> Result<char> {
+        let character = self.peek().ok_or_else(|| self.error("unexpected end of text"))?;
+        self.position += 1;
+        Ok(character)
+    }
+
+    pub(crate) fn expect(&mut self, expected: &str) -> Result<()> {
+        self.skip_whitespace();
+        for character in expected.chars() {
+            if self.peek() != Some(character) {
+                return Err(self.error(&format!("expected '{}'", expected)));
+            }
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    /// Skip the whitespace and comments, which last until the end of line
+    pub(crate) fn skip_whitespace(&mut self) {
+        let comment = match self.syntax {
+            Syntax::Erlang => '%',
+            Syntax::Elixir => '#',
+        };
+        while let Some(character) = self.peek() {
+            match character {
+                _ if character == comment => {
+                    while self.peek().is_some_and(|character| character != '\n') {
+                        self.position += 1;
+                    }
+                }
+                _ if character.is_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.peek().is_none()
+    }
+
+    /// The dot ends the term when it's followed by whitespace, a comment or
+    /// the end of text
+    fn parse_dot(&mut self) -> Result<()> {
+        self.expect(".")?;
+        match self.peek() {
+            None | Some('%') => Ok(()),
+            Some(character) if character.is_whitespace() => Ok(()),
+            Some(_) => Err(self.error("expected whitespace after the dot")),
+        }
+    }
+
+    pub(crate) fn parse_term(&mut self) -> Result<Term> {
+        if self.syntax == Syntax::Elixir {
+            return self.parse_elixir_term();
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.position += 1;
+                let (elements, _) = self.parse_elements('}', false)?;
+                Ok(Term::Tuple(elements))
+            }
+            Some('[') => {
+                self.position += 1;
+                match self.parse_elements(']', true)? {
+                    (elements, None) => Ok(Term::List(elements)),
+                    (elements, Some(tail)) => Ok(list_with_tail(elements, tail)),
+                }
+            }
+            Some('#') => {
+                self.position += 1;
+                self.expect("{")?;
+                self.parse_map()
+            }
+            Some('<') => {
+                self.expect("<<")?;
+                self.parse_binary()
+            }
+            Some('"') => {
+                let mut characters = vec![];
+                // Adjacent strings are joined, as in Erlang
+                while self.peek() == Some('"') {
+                    self.position += 1;
+                    characters.extend(self.parse_quoted('"')?);
+                    self.skip_whitespace();
+                }
+                Ok(string_term(characters))
+            }
+            Some('\'') => {
+                self.position += 1;
+                let name = self.parse_quoted('\'')?.into_iter().collect();
+                Ok(Term::Atom(name))
+            }
+            Some('$') => {
+                self.position += 1;
+                let character = match self.next()? {
+                    '\\' => self.parse_escape()?,
+                    character => character,
+                };
+                Ok(Term::Integer(character as i64))
+            }
+            Some('-') | Some('+') => {
+                let negative = self.next()? == '-';
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(character) if character.is_ascii_digit() => self.parse_number(negative),
+                    _ => Err(self.error("expected a number after the sign")),
+                }
+            }
+            Some(character) if character.is_ascii_digit() => self.parse_number(false),
+            Some(character) if is_lowercase(character) => {
+                let name = self.parse_name();
+                match name.as_str() {
+                    "fun" => self.parse_fun(),
+                    _ if RESERVED_WORDS.contains(&name.as_str()) => {
+                        Err(self.error(&format!("reserved word '{}' can't be an atom", name)))
+                    }
+                    _ => Ok(Term::Atom(name)),
+                }
+            }
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of text")),
+        }
+    }
+
+    /// Comma separated terms up to the closing bracket. Lists may end with
+    /// the `| Tail` part
+    pub(crate) fn parse_elements(&mut self, closing: char, is_list: bool) -> Result<(Vec<Term>, Option<Term>)> {
+        let mut elements = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(closing) {
+            self.position += 1;
+            return Ok((elements, None));
+        }
+
+        loop {
+            elements.push(self.parse_term()?);
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '|' if is_list => {
+                    let tail = self.parse_term()?;
+                    self.expect(&closing.to_string())?;
+                    return Ok((elements, Some(tail)));
+                }
+                character if character == closing => return Ok((elements, None)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error(&format!("expected ',' or '{}'", closing)));
+                }
+            }
+        }
+    }
+
+    fn parse_map(&mut self) -> Result<Term> {
+        let mut entries = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.position += 1;
+            return Ok(Term::Map(entries));
+        }
+
+        loop {
+            let key = self.parse_term()?;
+            self.expect("=>")?;
+            let value = self.parse_term()?;
+            insert_entry(&mut entries, key, value);
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(Term::Map(entries)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',' or '}'"));
+                }
+            }
+        }
+    }
+
+    /// Binary segments: bytes and strings. Strings with the `/utf8` type
+    /// are encoded as UTF-8, otherwise each character takes a byte, and
+    /// values which don't fit into a byte are truncated, as in Erlang. The
+    /// last byte can have a size in bits, like `<<1, 15:4>>`
+    fn parse_binary(&mut self) -> Result<Term> {
+        let mut bytes = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('>') {
+            self.expect(">>")?;
+            return Ok(Term::Binary(bytes));
+        }
+
+        loop {
+            match self.parse_term()? {
+                Term::Integer(value) => {
+                    self.skip_whitespace();
+                    if self.peek() == Some(':') {
+                        self.position += 1;
+                        let size = self.parse_term()?;
+                        return self.parse_bits_segment(bytes, value, size);
+                    }
+                    bytes.push(value as u8);
+                }
+                segment => {
+                    let characters = match segment {
+                        Term::String(characters) => characters.iter().map(|byte| *byte as char).collect(),
+                        Term::List(elements) => list_characters(&elements)
+                            .ok_or_else(|| self.error("expected a byte or a string in the binary"))?,
+                        _ => return Err(self.error("expected a byte or a string in the binary")),
+                    };
+                    self.skip_whitespace();
+                    if self.peek() == Some('/') {
+                        self.expect("/utf8")?;
+                        bytes.extend(characters.iter().collect::<String>().as_bytes());
+                    } else {
+                        bytes.extend(characters.iter().map(|character| *character as u32 as u8));
+                    }
+                }
+            }
+
+            self.skip_whitespace();
+            match self.next()? {
+                ',' => continue,
+                '>' if self.peek() == Some('>') => {
+                    self.position += 1;
+                    return Ok(Term::Binary(bytes));
+                }
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("expected ',' or '>>'"));
+                }
+            }
+        }
+    }
+
+    /// Finish the binary with the segment which has a size in bits. Only
+    /// the last segment can have it, so the size is up to 8 bits
+    pub(crate) fn parse_bits_segment(
+        &mut self, mut bytes: Vec<u8>, value: i64, size: Term
+    ) -> Result<Term> {
+        let bits = match size {
+            Term::Integer(bits @ 1..=8) => bits as u8,
+            _ => return Err(self.error("expected a size of 1 to 8 bits")),
+        };
+        self.skip_whitespace();
+        if self.peek() != Some('>') {
+            return Err(self.error("only the last segment can have a size"));
+        }
+        self.expect(">>")?;
+
+        bytes.push(((value as u64) << (8 - bits)) as u8);
+        match bits {
+            8 => Ok(Term::Binary(bytes)),
+            _ => Ok(Term::BitString(BertBitString::new(bytes, bits))),
+        }
+    }
+
+    /// Characters of the quoted string or atom, after the opening quote
+    pub(crate) fn parse_quoted(&mut self, quote: char) -> Result<Vec<char>> {
+        let mut characters = vec![];
+        loop {
+            match self.next()? {
+                '\\' => characters.push(self.parse_escape()?),
+                character if character == quote => return Ok(characters),
+                '#' if self.syntax == Syntax::Elixir && self.peek() == Some('{') => {
+                    return Err(self.error("string interpolation is not supported"));
+                }
+                character => characters.push(character),
+            }
+        }
+    }
+
+    /// Escape sequence after the backslash
+    pub(crate) fn parse_escape(&mut self) -> Result<char> {
+        let character = match self.next()? {
+            'b' => '\u{8}',
+            'd' => '\u{7f}',
+            'e' => '\u{1b}',
+            'f' => '\u{c}',
+            'n' => '\n',
+            'r' => '\r',
+            's' => ' ',
+            't' => '\t',
+            'v' => '\u{b}',
+            'a' if self.syntax == Syntax::Elixir => '\u{7}',
+            '0' if self.syntax == Syntax::Elixir => '\0',
+            'u' if self.syntax == Syntax::Elixir => {
+                let digits = match self.peek() {
+                    Some('{') => {
+                        self.position += 1;
+                        let digits = self.take_while(|character| character.is_ascii_hexdigit());
+                        self.expect("}")?;
+                        digits
+                    }
+                    _ => (0..4).filter_map(|_| self.next().ok()).collect(),
+                };
+                u32::from_str_radix(&digits, 16).ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid unicode escape"))?
+            }
+            '^' if self.syntax == Syntax::Erlang => {
+                let control = self.next()? as u32;
+                char::from_u32(control % 32).unwrap_or_default()
+            }
+            'x' => {
+                let digits = match self.peek() {
+                    Some('{') => {
+                        self.position += 1;
+                        let digits = self.take_while(|character| character.is_ascii_hexdigit());
+                        self.expect("}")?;
+                        digits
+                    }
+                    _ => {
+                        let mut digits = String::new();
+                        while digits.len() < 2 && self.peek().is_some_and(|character| character.is_ascii_hexdigit()) {
+                            digits.push(self.next()?);
+                        }
+                        digits
+                    }
+                };
+                u32::from_str_radix(&digits, 16).ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid hexadecimal escape"))?
+            }
+            first @ '0'..='7' if self.syntax == Syntax::Erlang => {
+                let mut code = first.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    match self.peek().and_then(|character| character.to_digit(8)) {
+                        Some(digit) => {
+                            code = code * 8 + digit;
+                            self.position += 1;
+                        }
+                        None => break,
+                    }
+                }
+                char::from_u32(code).unwrap_or_default()
+            }
+            character => character,
+        };
+        Ok(character)
+    }
+
+    pub(crate) fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> String {
+        let mut taken = String::new();
+        while let Some(character) = self.peek().filter(|character| predicate(*character)) {
+            taken.push(character);
+            self.position += 1;
+        }
+        taken
+    }
+
+    fn parse_name(&mut self) -> String {
+        self.take_while(is_name_character)
+    }
+
+    /// Digits with the optional `_` separators between them
+    pub(crate) fn parse_digits(&mut self, radix: u32) -> Result<String> {
+        let mut digits = self.take_while(|character| character.is_digit(radix));
+        while self.peek() == Some('_') && self.peek_at(1).is_some_and(|character| character.is_digit(radix)) {
+            self.position += 1;
+            digits.push_str(&self.take_while(|character| character.is_digit(radix)));
+        }
+        match digits.is_empty() {
+            true => Err(self.error("expected digits")),
+            false => Ok(digits),
+        }
+    }
+
+    /// Integer, integer with the base prefix (`16#FF` in Erlang, `0xFF` in
+    /// Elixir) or float
+    pub(crate) fn parse_number(&mut self, negative: bool) -> Result<Term> {
+        let sign = if negative { "-" } else { "" };
+
+        let prefix_radix = match (self.syntax, self.peek(), self.peek_at(1)) {
+            (Syntax::Elixir, Some('0'), Some('x')) => Some(16),
+            (Syntax::Elixir, Some('0'), Some('o')) => Some(8),
+            (Syntax::Elixir, Some('0'), Some('b')) => Some(2),
+            _ => None,
+        };
+        if let Some(radix) = prefix_radix {
+            self.position += 2;
+            let digits = self.parse_digits(radix)?;
+            let value = BigInt::from_str_radix(&format!("{}{}", sign, digits), radix)
+                .map_err(|_| self.error("invalid integer"))?;
+            return Ok(Term::from(value));
+        }
+
+        let digits = self.parse_digits(10)?;
+        if self.syntax == Syntax::Erlang && self.peek() == Some('#') {
+            self.position += 1;
+            let radix = digits.parse::<u32>().ok()
+                .filter(|radix| (2..=36).contains(radix))
+                .ok_or_else(|| self.error("base must be between 2 and 36"))?;
+            let digits = self.parse_digits(radix)?;
+            let value = BigInt::from_str_radix(&format!("{}{}", sign, digits), radix)
+                .map_err(|_| self.error("invalid integer"))?;
+            return Ok(Term::from(value));
+        }
+
+        if self.peek() == Some('.') && self.peek_at(1).is_some_and(|character| character.is_ascii_digit()) {
+            self.position += 1;
+            let fraction = self.parse_digits(10)?;
+            let mut text = format!("{}{}.{}", sign, digits, fraction);
+            if let Some('e' | 'E') = self.peek() {
+                self.position += 1;
+                text.push('e');
+                if let Some(character @ ('-' | '+')) = self.peek() {
+                    self.position += 1;
+                    text.push(character);
+                }
+                text.push_str(&self.parse_digits(10)?);
+            }
+            let value: f64 = text.parse()?;
+            return match value.is_finite() {
+                true => Ok(Term::Float(value)),
+                false => Err(self.error("float is out of range")),
+            };
+        }
+
+        let value = BigInt::from_str(&format!("{}{}", sign, digits))
+            .map_err(|_| self.error("invalid integer"))?;
+        Ok(Term::from(value))
+    }
+
+    /// External fun after the `fun` keyword: `fun Module:Function/Arity`
+    fn parse_fun(&mut self) -> Result<Term> {
+        let module = self.parse_atom()?;
+        self.expect(":")?;
+        let function = self.parse_atom()?;
+        self.expect("/")?;
+        self.skip_whitespace();
+        let arity = self.parse_digits(10)?.parse::<u8>()
+            .map_err(|_| self.error("arity must be between 0 and 255"))?;
+        Ok(Term::ExportFun(BertExportFun::new(&module, &function, arity)))
+    }
+
+    fn parse_atom(&mut self) -> Result<String> {
+        match self.parse_term()? {
+            Term::Atom(name) => Ok(name),
+            _ => Err(self.error("expected an atom")),
+        }
+    }
+}
+
+
+/// List of the elements and the tail. Elements of the list tail join the
+/// list, like `[a | [b]]` is the same as `[a, b]`
+pub(crate) fn list_with_tail(mut elements: Vec<Term>, tail: Term) -> Term {
+    match tail {
+        Term::List(tail) => {
+            elements.extend(tail);
+            Term::List(elements)
+        }
+        Term::String(bytes) => {
+            elements.extend(bytes.into_iter().map(|byte| Term::Integer(byte as i64)));
+            Term::List(elements)
+        }
+        Term::ImproperList(tail, last) => {
+            elements.extend(tail);
+            Term::ImproperList(elements, last)
+        }
+        tail => Term::ImproperList(elements, Box::new(tail)),
+    }
+}
+
+
+/// Add the map entry. The last value of the repeated key is kept, as in
+/// Erlang, where keys are the same when they are exactly equal (`"ab"` and
+/// `[97, 98]` are, `1` and `1.0` are not)
+pub(crate) fn insert_entry(entries: &mut Vec<(Term, Term)>, key: Term, value: Term) {
+    let is_same = |existing: &Term| compare(existing, &key, true) == Ordering::Equal;
+    match entries.iter_mut().find(|(existing, _)| is_same(existing)) {
+        Some(entry) => entry.1 = value,
+        None => entries.push((key, value)),
+    }
+}
+
+
+/// Characters of the list of integers
+pub(crate) fn list_characters(elements: &[Term]) -> Option<Vec<char>> {
+    elements.iter()
+        .map(|element| match element {
+            Term::Integer(value) => u32::try_from(*value).ok().and_then(char::from_u32),
+            _ => None,
+        })
+        .collect()
+}
+
+
+/// Strings of Latin-1 characters are encoded as STRING_EXT, the others as
+/// lists of integers
+pub(crate) fn string_term(characters: Vec<char>) -> Term {
+    let latin1: Option<Vec<u8>> = characters.iter()
+        .map(|character| u8::try_from(*character as u32).ok())
+        .collect();
+    match latin1 {
+        Some(bytes) if bytes.is_empty() => Term::List(vec![]),
+        Some(bytes) => Term::String(bytes),
+        None => Term::List(
+            characters.into_iter().map(|character| Term::Integer(character as i64)).collect()
+        ),
+    }
+}
@@ -0,0 +1,453 @@
+//! Erlang syntax for terms
+//!
+//! `Display` writes the term on a single line, the same way as
+//! `io_lib:format("~p")` does for short terms: `{reply,[{id,42}]}`. The
+//! alternate form (`{:#}`) and `Term::to_pretty_string` break the terms
+//! which don't fit into the line width over multiple lines, aligning the
+//! elements after the opening bracket. Terms are written in the Elixir
+//! syntax with `Syntax::Elixir`.
+//!
+//! Neither syntax has NaN and infinities, which can be decoded from
+//! NEW_FLOAT_EXT. They are written as the `nan`, `inf` and `-inf`
+//! placeholders, which don't read back as floats.
+use std::fmt;
+
+use crate::elixir::{write_elixir, write_elixir_pretty};
+use crate::order::compare;
+use crate::term::{Term, decode_term};
+use crate::types::BertBitString;
+
+
+/// Line width of the alternate `Display` form, the same as in `~p`
+pub const DEFAULT_LINE_WIDTH: usize = 80;
+
+/// Words which are written as quoted atoms
+pub(crate) const RESERVED_WORDS: [&str; 29] = [
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor",
+    "case", "catch", "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not",
+    "of", "or", "orelse", "receive", "rem", "try", "when", "xor",
+];
+
+
+/// Language which syntax is used to write and parse terms
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    #[default]
+    Erlang,
+    Elixir,
+}
+
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match f.alternate() {
+            true => f.write_str(&self.to_pretty_string(DEFAULT_LINE_WIDTH)),
+            false => f.write_str(&flat(self)),
+        }
+    }
+}
+
+
+impl Term {
+    /// Write the term in the Erlang syntax, breaking it over multiple lines
+    /// to fit into the width
+    pub fn to_pretty_string(&self, width: usize) -> String {
+        self.to_pretty_string_with_syntax(width, Syntax::Erlang)
+    }
+
+    /// Write the term on a single line in the syntax
+    pub fn to_string_with_syntax(&self, syntax: Syntax) -> String {
+        let mut output = String::new();
+        match syntax {
+            Syntax::Erlang => write_flat(&mut output, self),
+            Syntax::Elixir => write_elixir(&mut output, self),
+        }
+        output
+    }
+
+    /// Write the term in the syntax, breaking it over multiple lines to fit
+    /// into the width
+    pub fn to_pretty_string_with_syntax(&self, width: usize, syntax: Syntax) -> String {
+        let mut output = String::new();
+        match syntax {
+            Syntax::Erlang => write_pretty(&mut output, self, 0, width, 0),
+            Syntax::Elixir => write_elixir_pretty(&mut output, self, 0, width, 0),
+        }
+        output
+    }
+}
+
+
+pub(crate) fn is_lowercase(character: char) -> bool {
+    character.is_ascii_lowercase() || ('ß'..='ÿ').contains(&character) && character != '÷'
+}
+
+
+pub(crate) fn is_name_character(character: char) -> bool {
+    character.is_ascii_alphanumeric() || character == '_' || character == '@'
+        || ('À'..='ÿ').contains(&character) && character != '×' && character != '÷'
+}
+
+
+/// Check whether the atom is written without quotes: it starts with a
+/// lowercase letter, has only letters, digits, `_` and `@`, and is not a
+/// reserved word
+fn is_bare_atom(name: &str) -> bool {
+    let mut characters = name.chars();
+    match characters.next() {
+        Some(first) if is_lowercase(first) => {
+            characters.all(is_name_character) && !RESERVED_WORDS.contains(&name)
+        }
+        _ => false,
+    }
+}
+
+
+/// Printable characters of `io_lib:printable_latin1_list`
+fn is_printable(character: u32) -> bool {
+    matches!(character, 8..=13 | 27 | 32..=126 | 160..=255)
+}
+
+
+fn write_escaped(output: &mut String, character: char, quote: char) {
+    match character {
+        '\\' => output.push_str("\\\\"),
+        '\n' => output.push_str("\\n"),
+        '\r' => output.push_str("\\r"),
+        '\t' => output.push_str("\\t"),
+        '\u{b}' => output.push_str("\\v"),
+        '\u{8}' => output.push_str("\\b"),
+        '\u{c}' => output.push_str("\\f"),
+        '\u{1b}' => output.push_str("\\e"),
+        '\u{7f}' => output.push_str("\\d"),
+        _ if character == quote => {
+            output.push('\\');
+            output.push(quote);
+        }
+        '\u{0}'..='\u{1f}' | '\u{80}'..='\u{9f}' => {
+            output.push_str(&format!("\\{:03o}", character as u32));
+        }
+        _ => output.push(character),
+    }
+}
+
+
+pub(crate) fn write_atom(output: &mut String, name: &str) {
+    if is_bare_atom(name) {
+        output.push_str(name);
+        return;
+    }
+    output.push('\'');
+    for character in name.chars() {
+        write_escaped(output, character, '\'');
+    }
+    output.push('\'');
+}
+
+
+fn write_string<I: Iterator<Item = u32>>(output: &mut String, characters: I) {
+    output.push('"');
+    for character in characters {
+        write_escaped(output, char::from_u32(character).unwrap_or('?'), '"');
+    }
+    output.push('"');
+}
+
+
+/// Write the float with the shortest digits that read back to the same
+/// value, the same way as `io_lib_format:fwrite_g` does: the exponent is
+/// used when it takes less characters than the zeros
+pub(crate) fn write_float(output: &mut String, value: f64) {
+    if value.is_nan() {
+        output.push_str("nan");
+        return;
+    }
+    if value.is_infinite() {
+        output.push_str(if value > 0.0 { "inf" } else { "-inf" });
+        return;
+    }
+
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|character| *character != '.').collect();
+    let exponent: i64 = exponent.parse().unwrap_or(0);
+
+    // The value is 0.DIGITS * 10^place
+    let place = exponent + 1;
+    let length = digits.len() as i64;
+    let zeros_exponent = place - length;
+    let exponent_cost = zeros_exponent.to_string().len() as i64 + 2;
+
+    if value.is_sign_negative() {
+        output.push('-');
+    }
+    if value == 0.0 {
+        output.push_str("0.0");
+    } else if place <= 0 && zeros_exponent <= 0 && -place <= exponent_cost {
+        output.push_str("0.");
+        output.push_str(&"0".repeat(-place as usize));
+        output.push_str(&digits);
+    } else if place > 0 && place < length {
+        output.push_str(&digits[..place as usize]);
+        output.push('.');
+        output.push_str(&digits[place as usize..]);
+    } else if place > 0 && zeros_exponent <= exponent_cost {
+        output.push_str(&digits);
+        output.push_str(&"0".repeat(zeros_exponent as usize));
+        output.push_str(".0");
+    } else {
+        output.push_str(&digits[..1]);
+        output.push('.');
+        match &digits[1..] {
+            "" => output.push('0'),
+            rest => output.push_str(rest),
+        }
+        output.push_str(&format!("e{}", exponent));
+    }
+}
+
+
+fn write_binary(output: &mut String, bytes: &[u8], last_bits: Option<u8>) {
+    output.push_str("<<");
+    let printable = !bytes.is_empty() && bytes.iter().all(|byte| is_printable(*byte as u32));
+    match last_bits {
+        None if printable => write_string(output, bytes.iter().map(|byte| *byte as u32)),
+        None => {
+            let segments: Vec<String> = bytes.iter().map(u8::to_string).collect();
+            output.push_str(&segments.join(","));
+        }
+        Some(bits) => {
+            let (last, whole) = bytes.split_last().unwrap_or((&0, &[]));
+            for byte in whole {
+                output.push_str(&format!("{},", byte));
+            }
+            output.push_str(&format!("{}:{}", last >> (8 - bits), bits));
+        }
+    }
+    output.push_str(">>");
+}
+
+
+fn write_bitstring(output: &mut String, bitstring: &BertBitString) {
+    match bitstring.bits_in_last_byte {
+        bits @ 1..=7 if !bitstring.bytes.is_empty() => {
+            write_binary(output, &bitstring.bytes, Some(bits))
+        }
+        _ => write_binary(output, &bitstring.bytes, None),
+    }
+}
+
+
+/// Characters of the list, when all its elements are printable
+fn printable_characters(term: &Term) -> Option<Vec<u32>> {
+    let characters: Vec<u32> = match term {
+        Term::String(bytes) => bytes.iter().map(|byte| *byte as u32).collect(),
+        Term::List(elements) => elements.iter()
+            .map(|element| match element {
+                Term::Integer(value) => u32::try_from(*value).ok(),
+                _ => None,
+            })
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+    match !characters.is_empty() && characters.iter().all(|character| is_printable(*character)) {
+        true => Some(characters),
+        false => None,
+    }
+}
+
+
+/// Elements of the list which is not written as a string
+fn list_elements(term: &Term) -> Option<(Vec<Term>, Option<&Term>)> {
+    match term {
+        Term::String(bytes) => {
+            Some((bytes.iter().map(|byte| Term::Integer(*byte as i64)).collect(), None))
+        }
+        Term::List(elements) => Some((elements.clone(), None)),
+        Term::ImproperList(elements, tail) => Some((elements.clone(), Some(tail))),
+        _ => None,
+    }
+}
+
+
+/// Map entries sorted by their keys, as Erlang writes them
+pub(crate) fn sorted_entries(entries: &[(Term, Term)]) -> Vec<&(Term, Term)> {
+    let mut sorted: Vec<_> = entries.iter().collect();
+    sorted.sort_by(|left, right| compare(&left.0, &right.0, true));
+    sorted
+}
+
+
+/// Encoded closure field, written as a term
+pub(crate) fn flat_encoded(data: &[u8]) -> String {
+    match decode_term(data) {
+        Ok(Term::Atom(name)) => name,
+        Ok(term) => flat(&term),
+        Err(_) => "?".to_string(),
+    }
+}
+
+
+/// Write the term on a single line
+pub(crate) fn write_flat(output: &mut String, term: &Term) {
+    match term {
+        Term::Atom(name) => write_atom(output, name),
+        Term::Integer(value) => output.push_str(&value.to_string()),
+        Term::BigInteger(value) => output.push_str(&value.to_string()),
+        Term::Float(value) => write_float(output, *value),
+        Term::Binary(bytes) => write_binary(output, bytes, None),
+        Term::BitString(bitstring) => write_bitstring(output, bitstring),
+        Term::String(_) | Term::List(_) | Term::ImproperList(..) => {
+            if let Some(characters) = printable_characters(term) {
+                return write_string(output, characters.into_iter());
+            }
+            let (elements, tail) = list_elements(term).unwrap_or_default();
+            output.push('[');
+            write_flat_elements(output, &elements);
+            if let Some(tail) = tail {
+                output.push('|');
+                write_flat(output, tail);
+            }
+            output.push(']');
+        }
+        Term::Tuple(elements) => {
+            output.push('{');
+            write_flat_elements(output, elements);
+            output.push('}');
+        }
+        Term::Map(entries) => {
+            output.push_str("#{");
+            for (index, (key, value)) in sorted_entries(entries).into_iter().enumerate() {
+                if index > 0 {
+                    output.push(',');
+                }
+                write_flat(output, key);
+                output.push_str(" => ");
+                write_flat(output, value);
+            }
+            output.push('}');
+        }
+        Term::Pid(pid) => output.push_str(&format!("<0.{}.{}>", pid.id, pid.serial)),
+        Term::Reference(reference) => {
+            let words: Vec<String> = reference.id.iter().rev().map(u32::to_string).collect();
+            output.push_str(&format!("#Ref<0.{}>", words.join(".")));
+        }
+        Term::Port(port) => output.push_str(&format!("#Port<0.{}>", port.id)),
+        Term::ExportFun(fun) => {
+            output.push_str("fun ");
+            write_atom(output, &fun.module);
+            output.push(':');
+            write_atom(output, &fun.function);
+            output.push_str(&format!("/{}", fun.arity));
+        }
+        Term::Closure(closure) => {
+            output.push_str(&format!(
+                "#Fun<{}.{}.{}>",
+                flat_encoded(&closure.module),
+                flat_encoded(&closure.old_index),
+                flat_encoded(&closure.old_uniq),
+            ));
+        }
+    }
+}
+
+
+fn write_flat_elements(output: &mut String, elements: &[Term]) {
+    for (index, element) in elements.iter().enumerate() {
+        if index > 0 {
+            output.push(',');
+        }
+        write_flat(output, element);
+    }
+}
+
+
+pub(crate) fn flat(term: &Term) -> String {
+    let mut output = String::new();
+    write_flat(&mut output, term);
+    output
+}
+
+
+fn new_line(output: &mut String, column: usize) {
+    output.push_str(",\n");
+    output.push_str(&" ".repeat(column));
+}
+
+
+/// Write the term starting at the column. `suffix` is the number of
+/// characters which follow the term on the same line, like closing brackets
+fn write_pretty(output: &mut String, term: &Term, column: usize, width: usize, suffix: usize) {
+    let single_line = flat(term);
+    if column + single_line.chars().count() + suffix <= width {
+        output.push_str(&single_line);
+        return;
+    }
+
+    match term {
+        Term::Tuple(elements) if !elements.is_empty() => {
+            output.push('{');
+            let mut elements = elements.as_slice();
+            let mut column = column + 1;
+            // Tagged tuples keep the tag on the first line, the rest of the
+            // elements are aligned after it
+            if let (Term::Atom(_), true) = (&elements[0], elements.len() > 1) {
+                let tag = flat(&elements[0]);
+                output.push_str(&tag);
+                output.push(',');
+                column += tag.chars().count() + 1;
+                elements = &elements[1..];
+            }
+            write_pretty_elements(output, elements, column, width, suffix + 1);
+            output.push('}');
+        }
+        Term::String(_) | Term::List(_) | Term::ImproperList(..) => {
+            match (printable_characters(term), list_elements(term)) {
+                (None, Some((elements, tail))) if !elements.is_empty() => {
+                    output.push('[');
+                    let tail = tail.map(flat);
+                    let tail_length = tail.as_ref().map_or(0, |tail| tail.chars().count() + 1);
+                    write_pretty_elements(output, &elements, column + 1, width, suffix + tail_length + 1);
+                    if let Some(tail) = tail {
+                        output.push('|');
+                        output.push_str(&tail);
+                    }
+                    output.push(']');
+                }
+                _ => output.push_str(&single_line),
+            }
+        }
+        Term::Map(entries) if !entries.is_empty() => {
+            output.push_str("#{");
+            let column = column + 2;
+            let entries = sorted_entries(entries);
+            for (index, (key, value)) in entries.iter().enumerate() {
+                if index > 0 {
+                    new_line(output, column);
+                }
+                let key = flat(key);
+                output.push_str(&key);
+                output.push_str(" => ");
+                let entry_suffix = if index + 1 == entries.len() { suffix + 1 } else { 1 };
+                write_pretty(output, value, column + key.chars().count() + 4, width, entry_suffix);
+            }
+            output.push('}');
+        }
+        _ => output.push_str(&single_line),
+    }
+}
+
+
+fn write_pretty_elements(output: &mut String, elements: &[Term], column: usize, width: usize, suffix: usize) {
+    for (index, element) in elements.iter().enumerate() {
+        if index > 0 {
+            new_line(output, column);
+        }
+        let element_suffix = match index + 1 == elements.len() {
+            true => suffix,
+            false => 1,
+        };
+        write_pretty(output, element, column, width, element_suffix);
+    }
+}
+
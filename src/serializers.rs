@@ -1,24 +1,33 @@
 //! BERT serialization
 //!
 //! This module provides for BERT serialization with the type `Serializer`.
-use std::io;
+use std::io::{self, Write};
 use std::vec::Vec;
 
 use byteorder::{BigEndian, WriteBytesExt};
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use num::bigint::BigInt;
 use serde::ser;
 
 use crate::errors::{Error, Result};
-use crate::types::{ETF_VERSION, BertTag};
+use crate::order::sort_map_entries;
+use crate::types::{BERT_LABEL, ETF_VERSION, MAX_ATOM_LENGTH, BertTag};
 use crate::utils::{
-    merge_terms, str_to_binary, get_atom, get_nil, get_bert_nil,
-    get_bert_atom, get_empty_tuple, get_small_tuple
+    merge_terms, get_string, to_latin1, get_exact_atom, get_small_atom, get_small_utf8_atom,
+    get_legacy_float, get_integer, get_bignum, get_nil, get_empty_tuple
 };
 use crate::wrappers::{
-    BIGNUM_STRUCT_NAME, TIME_STRUCT_NAME, REGEX_STRUCT_NAME,
-    REGEX_OPTION_ENUM_NAME
+    BIGNUM_STRUCT_NAME, TIME_STRUCT_NAME, REGEX_STRUCT_NAME, REGEX_OPTION_ENUM_NAME,
+    PID_STRUCT_NAME, LEGACY_PID_STRUCT_NAME, REFERENCE_STRUCT_NAME, PORT_STRUCT_NAME,
+    V4_PORT_STRUCT_NAME, BITSTRING_STRUCT_NAME,
+    EXPORT_FUN_STRUCT_NAME, CLOSURE_STRUCT_NAME, IMPROPER_LIST_STRUCT_NAME, TERM_STRUCT_NAME
 };
 
 
+/// Maximum number of atoms in the atom cache of a distribution header
+pub(crate) const MAX_ATOM_CACHE_REFS: usize = 255;
+
 #[doc(hidden)]
 #[derive(Eq, PartialEq)]
 pub enum State {
@@ -37,7 +46,7 @@ impl<W: io::Write> BigNumSerializer<W> {
     }
 }
 
-impl<'a, W: io::Write> ser::Serializer for &'a mut BigNumSerializer<W> {
+impl<W: io::Write> ser::Serializer for &mut BigNumSerializer<W> {
     type Ok = ();
     type Error = Error;
 
@@ -121,18 +130,12 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut BigNumSerializer<W> {
     }
 }
 
-struct RegexOptionSerializer<W> {
-    writer: W,
-}
-
-impl<W: io::Write> RegexOptionSerializer<W> {
-    pub fn new(writer: W) -> Self {
-        RegexOptionSerializer { writer }
-    }
+struct RegexOptionSerializer<'a, W: 'a> {
+    ser: &'a mut Serializer<W>,
 }
 
 struct RegexOptionSeqSerializer<'a, W: 'a> {
-    ser: &'a mut RegexOptionSerializer<W>,
+    ser: &'a mut Serializer<W>,
     state: State,
 }
 
@@ -142,7 +145,7 @@ impl<'a, W: io::Write> ser::SerializeSeq for RegexOptionSeqSerializer<'a, W> {
 
     fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
         self.state = State::Rest;
-        value.serialize(&mut *self.ser)
+        value.serialize(RegexOptionSerializer { ser: &mut *self.ser })
     }
 
     fn end(self) -> Result<()> {
@@ -157,7 +160,7 @@ impl<'a, W: io::Write> ser::SerializeSeq for RegexOptionSeqSerializer<'a, W> {
     }
 }
 
-impl<'a, W: io::Write> ser::Serializer for &'a mut RegexOptionSerializer<W> {
+impl<'a, W: io::Write> ser::Serializer for RegexOptionSerializer<'a, W> {
     type Ok = ();
     type Error = Error;
 
@@ -186,8 +189,7 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut RegexOptionSerializer<W> {
     fn serialize_none(self) -> Result<()> { Err(Error::UnsupportedType) }
 
     fn serialize_str(self, value: &str) -> Result<()> {
-        let value = get_atom(value);
-        self.writer.write_all(value.as_slice()).map_err(From::from)
+        self.ser.write_atom(&value.to_lowercase())
     }
 
     fn serialize_some<T: ?Sized + ser::Serialize>(self, _value: &T) -> Result<()> {
@@ -213,17 +215,16 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut RegexOptionSerializer<W> {
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
         match len {
             Some(0) => {
-                let bert_nil_tuple = get_bert_nil();
-                self.writer.write_all(bert_nil_tuple.as_slice())?;
-                Ok(RegexOptionSeqSerializer { ser: self, state: State::Empty })
+                self.ser.write_bert_header(2, "nil")?;
+                Ok(RegexOptionSeqSerializer { ser: self.ser, state: State::Empty })
             }
             Some(list_length) => {
                 let mut header = vec![BertTag::List as u8];
                 header.write_i32::<BigEndian>(list_length as i32).unwrap();
-                self.writer.write_all(header.as_slice())?;
-                Ok(RegexOptionSeqSerializer { ser: self, state: State::First })
+                self.ser.writer.write_all(header.as_slice())?;
+                Ok(RegexOptionSeqSerializer { ser: self.ser, state: State::First })
             }
-            None => Ok(RegexOptionSeqSerializer { ser: self, state: State::Empty })
+            None => Ok(RegexOptionSeqSerializer { ser: self.ser, state: State::Empty })
         }
     }
 
@@ -252,13 +253,197 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut RegexOptionSerializer<W> {
     }
 }
 
+/// Encoding which is used for Rust maps and structs
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum MapEncoding {
+    /// Maps are written as `{bert, dict, [{Key, Value}, ...]}` and structs
+    /// as `{name, {field, value}, ...}` tuples
+    #[default]
+    BertDict,
+    /// Maps and structs are written as native Erlang maps (MAP_EXT).
+    /// Struct fields become atom keys
+    Native,
+}
+
+
+/// Version of the encoding, like `{minor_version, Version}` option of
+/// `erlang:term_to_binary`
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum MinorVersion {
+    /// Floats are written as text (FLOAT_EXT), atoms are written as
+    /// Latin-1 when possible
+    V0,
+    /// Floats are written as NEW_FLOAT_EXT, atoms are written as Latin-1
+    /// when possible
+    #[default]
+    V1,
+    /// Floats are written as NEW_FLOAT_EXT, atoms are always written with
+    /// the UTF-8 tags (SMALL_ATOM_UTF8_EXT for atoms up to 255 bytes). The
+    /// default of `erlang:term_to_binary` since OTP 26
+    V2,
+}
+
+
+/// Options that control how values are encoded by the `Serializer`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SerializerOptions {
+    pub map_encoding: MapEncoding,
+    pub minor_version: MinorVersion,
+    /// Write atoms up to 255 bytes as SMALL_ATOM_EXT (SMALL_ATOM_UTF8_EXT
+    /// for non Latin-1 names). The BERT specification only allows ATOM_EXT,
+    /// and BERT decoders outside of Erlang reject the other atom tags, so
+    /// this is disabled by default. Enable it when the peer is Erlang/OTP,
+    /// which accepts these tags since R16
+    pub small_atoms: bool,
+    /// Pick the same tags as `erlang:term_to_binary` does, so the output is
+    /// byte-identical to the Erlang one: integers and tuples use the
+    /// smallest tag that fits, atoms are written like the given minor
+    /// version does, lists of bytes become STRING_EXT and empty lists NIL_EXT.
+    /// Takes precedence over `small_atoms`
+    pub canonical: bool,
+    /// Sort map entries by the Erlang term order of their keys, like the
+    /// `deterministic` option of `erlang:term_to_binary`, so the same map
+    /// is always encoded to the same bytes. Applies to Rust maps with
+    /// both encodings and to structs written as native maps
+    pub deterministic: bool,
+}
+
+
+/// Serializer for the fields of Erlang specific types (pids, references, ...)
+/// which are laid out like the wire format: strings are written as atoms,
+/// integers as fixed-width big-endian values and bytes without any header.
+/// Newtype structs are delegated to the parent serializer as regular terms
+struct ExtensionFieldSerializer<'a, W: 'a> {
+    ser: &'a mut Serializer<W>,
+}
+
+struct ExtensionSeqSerializer<'a, W: 'a> {
+    ser: &'a mut Serializer<W>,
+}
+
+impl<'a, W: io::Write> ser::SerializeSeq for ExtensionSeqSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(ExtensionFieldSerializer { ser: &mut *self.ser })
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, W: io::Write> ser::Serializer for ExtensionFieldSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = ExtensionSeqSerializer<'a, W>;
+    type SerializeTuple = ser::Impossible<(), Error>;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = ser::Impossible<(), Error>;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_i8(self, _v: i8) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_i16(self, _v: i16) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_i32(self, _v: i32) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_i64(self, _v: i64) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_f32(self, _v: f32) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_f64(self, _v: f64) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_char(self, _v: char) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_unit(self) -> Result<()> { Err(Error::UnsupportedType) }
+    fn serialize_none(self) -> Result<()> { Err(Error::UnsupportedType) }
+
+    fn serialize_u8(self, value: u8) -> Result<()> {
+        self.ser.writer.write_u8(value).map_err(From::from)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.ser.writer.write_u16::<BigEndian>(value).map_err(From::from)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.ser.writer.write_u32::<BigEndian>(value).map_err(From::from)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.ser.writer.write_u64::<BigEndian>(value).map_err(From::from)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<()> {
+        self.ser.write_atom(value)
+    }
+
+    fn serialize_bytes(self, data: &[u8]) -> Result<()> {
+        self.ser.writer.write_all(data).map_err(From::from)
+    }
+
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, _value: &T) -> Result<()> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _idx: u32, _variant: &'static str) -> Result<()> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self.ser)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(self, _name: &'static str, _idx: u32, _variant: &'static str, _value: &T) -> Result<()> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(ExtensionSeqSerializer { ser: self.ser })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _idx: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _idx: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+        Err(Error::UnsupportedType)
+    }
+}
+
 pub struct Serializer<W> {
     writer: W,
+    options: SerializerOptions,
+    atom_cache_refs: Option<Vec<String>>,
 }
 
 impl<W: io::Write> Serializer<W> {
     pub fn new(writer: W) -> Serializer<W> {
-        Serializer { writer }
+        Serializer::with_options(writer, SerializerOptions::default())
+    }
+
+    /// Creates the BERT serializer with the specified encoding options
+    pub fn with_options(writer: W, options: SerializerOptions) -> Serializer<W> {
+        Serializer { writer, options, atom_cache_refs: None }
     }
 
     /// Unwrap the `Writer` from the `Serializer`.
@@ -272,6 +457,158 @@ impl<W: io::Write> Serializer<W> {
         let binary = merge_terms(header, data);
         self.writer.write_all(binary.as_slice()).map_err(From::from)
     }
+
+    /// Write atoms as ATOM_CACHE_REF, collecting them into the table of
+    /// the distribution header
+    pub(crate) fn enable_atom_cache_refs(&mut self) {
+        self.atom_cache_refs = Some(Vec::new());
+    }
+
+    /// Atoms referenced by ATOM_CACHE_REF, in the order of their indexes
+    pub(crate) fn take_atom_cache_refs(&mut self) -> Vec<String> {
+        self.atom_cache_refs.take().unwrap_or_default()
+    }
+
+    /// Write the atom as-is. When the atom cache references are enabled,
+    /// the atom is written as a reference, until the table is full
+    fn write_atom(&mut self, name: &str) -> Result<()> {
+        if name.chars().count() > MAX_ATOM_LENGTH {
+            return Err(Error::AtomTooLong(name.to_string()));
+        }
+
+        if let Some(refs) = self.atom_cache_refs.as_mut() {
+            let index = match refs.iter().position(|atom| atom == name) {
+                Some(index) => Some(index),
+                None if refs.len() < MAX_ATOM_CACHE_REFS => {
+                    refs.push(name.to_string());
+                    Some(refs.len() - 1)
+                }
+                None => None,
+            };
+            if let Some(index) = index {
+                let binary = vec![BertTag::AtomCacheRef as u8, index as u8];
+                return self.writer.write_all(binary.as_slice()).map_err(From::from);
+            }
+        }
+
+        let atom = match self.options.minor_version {
+            MinorVersion::V2 => get_small_utf8_atom(name),
+            // Erlang keeps ATOM_EXT for Latin-1 atoms, but other atoms are
+            // written with the UTF-8 tags
+            _ if self.options.canonical => match to_latin1(name) {
+                Some(_) => get_exact_atom(name),
+                None => get_small_utf8_atom(name),
+            },
+            _ if self.options.small_atoms => get_small_atom(name),
+            MinorVersion::V0 | MinorVersion::V1 => get_exact_atom(name),
+        }?;
+        self.writer.write_all(atom.as_slice()).map_err(From::from)
+    }
+
+    /// Write the header of a tuple with the given arity. Records and enum
+    /// variants are written as LARGE_TUPLE_EXT, unless the canonical
+    /// encoding is enabled
+    fn write_tuple_header(&mut self, arity: usize) -> Result<()> {
+        let mut header = vec![];
+        if self.options.canonical && arity <= u8::MAX as usize {
+            header.push(BertTag::SmallTuple as u8);
+            header.push(arity as u8);
+        } else {
+            header.push(BertTag::LargeTuple as u8);
+            header.write_i32::<BigEndian>(arity as i32).unwrap();
+        }
+        self.writer.write_all(header.as_slice()).map_err(From::from)
+    }
+
+    /// Encode a term separately with the same options. Atom cache
+    /// references are shared with the nested serializer
+    fn encode_nested_with<F>(&mut self, encode: F) -> Result<Vec<u8>>
+        where F: FnOnce(&mut Serializer<Vec<u8>>) -> Result<()>
+    {
+        let mut nested = Serializer::with_options(Vec::new(), self.options.clone());
+        nested.atom_cache_refs = self.atom_cache_refs.take();
+        let result = encode(&mut nested);
+        self.atom_cache_refs = nested.atom_cache_refs.take();
+        result?;
+        Ok(nested.into_inner())
+    }
+
+    /// Encode the value separately with the same options
+    fn encode_nested<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<Vec<u8>> {
+        self.encode_nested_with(|nested| value.serialize(nested))
+    }
+
+    /// Write the encoded map entries, either as MAP_EXT or as the list of
+    /// the `{bert, dict, ...}` tuple. Entries are sorted by their keys for
+    /// the deterministic encoding
+    fn write_map_entries(&mut self, mut entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        if self.options.deterministic {
+            let atoms = self.atom_cache_refs.as_deref().unwrap_or_default();
+            entries = sort_map_entries(entries, atoms)?;
+        }
+
+        let mut binary = vec![];
+        match self.options.map_encoding {
+            MapEncoding::Native => {
+                binary.push(BertTag::Map as u8);
+                binary.write_u32::<BigEndian>(entries.len() as u32).unwrap();
+                for (key, value) in entries {
+                    binary.extend(key);
+                    binary.extend(value);
+                }
+            }
+            MapEncoding::BertDict if entries.is_empty() => {
+                binary.extend(get_nil());
+            }
+            MapEncoding::BertDict => {
+                binary.push(BertTag::List as u8);
+                binary.write_u32::<BigEndian>(entries.len() as u32).unwrap();
+                for (key, value) in entries {
+                    binary.extend([BertTag::SmallTuple as u8, 2u8]);
+                    binary.extend(key);
+                    binary.extend(value);
+                }
+                binary.extend(get_nil());
+            }
+        }
+        self.writer.write_all(binary.as_slice()).map_err(From::from)
+    }
+
+    /// Write the encoded elements as a list with the smallest tag: NIL_EXT
+    /// for the empty list, STRING_EXT when every element is a small
+    /// integer and LIST_EXT otherwise
+    fn write_list(&mut self, elements: Vec<Vec<u8>>) -> Result<()> {
+        if elements.is_empty() {
+            let nil = get_nil();
+            return self.writer.write_all(nil.as_slice()).map_err(From::from);
+        }
+
+        let is_small_integer = |element: &Vec<u8>| {
+            element.len() == 2 && element[0] == BertTag::SmallInteger as u8
+        };
+        if elements.len() <= u16::MAX as usize && elements.iter().all(is_small_integer) {
+            let mut binary = vec![BertTag::String as u8];
+            binary.write_u16::<BigEndian>(elements.len() as u16).unwrap();
+            binary.extend(elements.iter().map(|element| element[1]));
+            return self.writer.write_all(binary.as_slice()).map_err(From::from);
+        }
+
+        let mut header = vec![BertTag::List as u8];
+        header.write_u32::<BigEndian>(elements.len() as u32).unwrap();
+        self.writer.write_all(header.as_slice())?;
+        for element in elements {
+            self.writer.write_all(element.as_slice())?;
+        }
+        let nil = get_nil();
+        self.writer.write_all(nil.as_slice()).map_err(From::from)
+    }
+
+    /// Write the header of `{bert, kind, ...}` tuple with the given arity
+    fn write_bert_header(&mut self, arity: u8, kind: &str) -> Result<()> {
+        self.writer.write_all(&[BertTag::SmallTuple as u8, arity])?;
+        self.write_atom(BERT_LABEL)?;
+        self.write_atom(kind)
+    }
 }
 
 
@@ -279,6 +616,8 @@ impl<W: io::Write> Serializer<W> {
 pub struct SeqSerializer<'a, W: 'a> {
     ser: &'a mut Serializer<W>,
     state: State,
+    /// Encoded elements, collected only for the canonical encoding
+    elements: Option<Vec<Vec<u8>>>,
 }
 
 pub struct TupleSerializer<'a, W: 'a> {
@@ -296,6 +635,11 @@ pub struct TupleVariantSerializer<'a, W: 'a> {
 pub struct MapSerializer<'a, W: 'a> {
     ser: &'a mut Serializer<W>,
     state: State,
+    /// Encoded entries, collected for the deterministic encoding and for
+    /// maps of unknown length
+    entries: Option<Vec<(Vec<u8>, Vec<u8>)>>,
+    /// Encoded key of the entry, which value is not written yet
+    key: Option<Vec<u8>>,
 }
 
 pub enum StructSerializer<'a, W: 'a> {
@@ -303,6 +647,11 @@ pub enum StructSerializer<'a, W: 'a> {
     Regular { ser: &'a mut Serializer<W> },
     /// BERT special types (time, regex): fields are raw values
     Bert { ser: &'a mut Serializer<W> },
+    /// Native map: fields are written as Atom(field_name) => value pairs.
+    /// Encoded pairs are collected for the deterministic encoding
+    Map { ser: &'a mut Serializer<W>, entries: Option<Vec<(Vec<u8>, Vec<u8>)>> },
+    /// Erlang specific types (pids, ...): fields are written in the wire layout
+    Extension { ser: &'a mut Serializer<W> },
 }
 
 pub struct StructVariantSerializer<'a, W: 'a> {
@@ -316,10 +665,18 @@ impl<'a, W: io::Write> ser::SerializeSeq for SeqSerializer<'a, W> {
 
     fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
         self.state = State::Rest;
+        if let Some(elements) = self.elements.as_mut() {
+            elements.push(self.ser.encode_nested(value)?);
+            return Ok(());
+        }
         value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<()> {
+        if let Some(elements) = self.elements {
+            return self.ser.write_list(elements);
+        }
+
         match self.state {
             State::Empty => Ok(()),
             _ => {
@@ -380,17 +737,37 @@ impl<'a, W: io::Write> ser::SerializeMap for MapSerializer<'a, W> {
 
     fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<()> {
         self.state = State::Rest;
-        let tuple_header = vec![BertTag::SmallTuple as u8, 2u8];
-        self.ser.writer.write_all(tuple_header.as_slice())?;
+        if self.entries.is_some() {
+            self.key = Some(self.ser.encode_nested(key)?);
+            return Ok(());
+        }
+
+        if self.ser.options.map_encoding == MapEncoding::BertDict {
+            let tuple_header = vec![BertTag::SmallTuple as u8, 2u8];
+            self.ser.writer.write_all(tuple_header.as_slice())?;
+        }
         key.serialize(&mut *self.ser)
     }
 
     fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        if let Some(entries) = self.entries.as_mut() {
+            let key = self.key.take().ok_or_else(|| {
+                Error::Custom("map value is serialized before its key".to_string())
+            })?;
+            entries.push((key, self.ser.encode_nested(value)?));
+            return Ok(());
+        }
+
         value.serialize(&mut *self.ser)
     }
 
     fn end(self) -> Result<()> {
-        if self.state == State::Rest {
+        if let Some(entries) = self.entries {
+            return self.ser.write_map_entries(entries);
+        }
+
+        let is_dict = self.ser.options.map_encoding == MapEncoding::BertDict;
+        if is_dict && self.state == State::Rest {
             let nil_atom = get_nil();
             self.ser.writer.write_all(nil_atom.as_slice())?;
         }
@@ -408,20 +785,33 @@ impl<'a, W: io::Write> ser::SerializeStruct for StructSerializer<'a, W> {
             StructSerializer::Regular { ser } => {
                 let header = vec![BertTag::SmallTuple as u8, 2u8];
                 ser.writer.write_all(header.as_slice())?;
-
-                let field_atom = get_atom(key);
-                ser.writer.write_all(field_atom.as_slice())?;
+                ser.write_atom(&key.to_lowercase())?;
 
                 value.serialize(&mut **ser)
             }
             StructSerializer::Bert { ser } => {
                 value.serialize(&mut **ser)
             }
+            StructSerializer::Map { ser, entries: Some(entries) } => {
+                let key = ser.encode_nested_with(|nested| nested.write_atom(&key.to_lowercase()))?;
+                entries.push((key, ser.encode_nested(value)?));
+                Ok(())
+            }
+            StructSerializer::Map { ser, entries: None } => {
+                ser.write_atom(&key.to_lowercase())?;
+                value.serialize(&mut **ser)
+            }
+            StructSerializer::Extension { ser } => {
+                value.serialize(ExtensionFieldSerializer { ser: &mut **ser })
+            }
         }
     }
 
     fn end(self) -> Result<()> {
-        Ok(())
+        match self {
+            StructSerializer::Map { ser, entries: Some(entries) } => ser.write_map_entries(entries),
+            _ => Ok(()),
+        }
     }
 }
 
@@ -433,9 +823,7 @@ impl<'a, W: io::Write> ser::SerializeStructVariant for StructVariantSerializer<'
     fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
         let header = vec![BertTag::SmallTuple as u8, 2u8];
         self.ser.writer.write_all(header.as_slice())?;
-
-        let field_atom = get_atom(key);
-        self.ser.writer.write_all(field_atom.as_slice())?;
+        self.ser.write_atom(&key.to_lowercase())?;
 
         value.serialize(&mut *self.ser)
     }
@@ -460,13 +848,7 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     #[inline]
     fn serialize_bool(self, value: bool) -> Result<()> {
         let boolean_string = value.to_string();
-
-        let bert_atom = get_bert_atom();
-        let boolean_atom = get_atom(&boolean_string);
-
-        let binary = merge_terms(bert_atom, boolean_atom);
-        let tuple = get_small_tuple(2, binary);
-        self.writer.write_all(tuple.as_slice()).map_err(From::from)
+        self.write_bert_header(2, &boolean_string)
     }
 
     #[inline]
@@ -481,6 +863,10 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_i32(self, value: i32) -> Result<()> {
+        if self.options.canonical {
+            return self.serialize_i128(value as i128);
+        }
+
         let mut binary = vec![];
         binary.write_i32::<BigEndian>(value).unwrap();
         self.generate_term(BertTag::Integer, binary)?;
@@ -488,8 +874,14 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     #[inline]
-    fn serialize_i64(self, _value: i64) -> Result<()> {
-        Err(Error::UnsupportedType)
+    fn serialize_i64(self, value: i64) -> Result<()> {
+        self.serialize_i128(value as i128)
+    }
+
+    #[inline]
+    fn serialize_i128(self, value: i128) -> Result<()> {
+        let binary = get_integer(value);
+        self.writer.write_all(binary.as_slice()).map_err(From::from)
     }
 
     #[inline]
@@ -498,18 +890,27 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     #[inline]
-    fn serialize_u16(self, _value: u16) -> Result<()> {
-        Err(Error::UnsupportedType)
+    fn serialize_u16(self, value: u16) -> Result<()> {
+        self.serialize_i64(value as i64)
     }
 
     #[inline]
-    fn serialize_u32(self, _value: u32) -> Result<()> {
-        Err(Error::UnsupportedType)
+    fn serialize_u32(self, value: u32) -> Result<()> {
+        self.serialize_i64(value as i64)
     }
 
     #[inline]
-    fn serialize_u64(self, _value: u64) -> Result<()> {
-        Err(Error::UnsupportedType)
+    fn serialize_u64(self, value: u64) -> Result<()> {
+        self.serialize_i128(value as i128)
+    }
+
+    #[inline]
+    fn serialize_u128(self, value: u128) -> Result<()> {
+        let binary = match i128::try_from(value) {
+            Ok(value) => get_integer(value),
+            Err(_) => get_bignum(&BigInt::from(value)),
+        };
+        self.writer.write_all(binary.as_slice()).map_err(From::from)
     }
 
     #[inline]
@@ -519,6 +920,11 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_f64(self, value: f64) -> Result<()> {
+        if self.options.minor_version == MinorVersion::V0 {
+            let binary = get_legacy_float(value)?;
+            return self.writer.write_all(binary.as_slice()).map_err(From::from);
+        }
+
         let mut binary = vec![];
         binary.write_f64::<BigEndian>(value).unwrap();
         self.generate_term(BertTag::NewFloat, binary)
@@ -531,8 +937,8 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_str(self, value: &str) -> Result<()> {
-        let binary_string = str_to_binary(value);
-        self.generate_term(BertTag::String, binary_string)
+        let binary = get_string(value);
+        self.writer.write_all(binary.as_slice()).map_err(From::from)
     }
 
     #[inline]
@@ -574,20 +980,17 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         self, name: &'static str, value: &T
     ) -> Result<()> {
         match name {
-            BIGNUM_STRUCT_NAME => {
+            BIGNUM_STRUCT_NAME | TERM_STRUCT_NAME => {
                 let mut bignum_serializer = BigNumSerializer::new(&mut self.writer);
                 value.serialize(&mut bignum_serializer)
             },
             REGEX_OPTION_ENUM_NAME => {
-                let mut regex_options_serializer = RegexOptionSerializer::new(&mut self.writer);
-                value.serialize(&mut regex_options_serializer)
+                value.serialize(RegexOptionSerializer { ser: self })
             },
             _ => {
                 let header = vec![BertTag::SmallTuple as u8, 2u8];
                 self.writer.write_all(header.as_slice())?;
-
-                let structure_name_atom = get_atom(name);
-                self.writer.write_all(structure_name_atom.as_slice())?;
+                self.write_atom(&name.to_lowercase())?;
 
                 value.serialize(self)
             }
@@ -601,15 +1004,11 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     ) -> Result<()> {
         let header = vec![BertTag::SmallTuple as u8, 2u8];
         self.writer.write_all(header.as_slice())?;
-
-        let enum_atom = get_atom(name);
-        self.writer.write_all(enum_atom.as_slice())?;
+        self.write_atom(&name.to_lowercase())?;
 
         let variant_header = vec![BertTag::SmallTuple as u8, 2u8];
         self.writer.write_all(variant_header.as_slice())?;
-
-        let variant_atom = get_atom(variant);
-        self.writer.write_all(variant_atom.as_slice())?;
+        self.write_atom(&variant.to_lowercase())?;
 
         value.serialize(self)
     }
@@ -626,19 +1025,24 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
 
     #[inline]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        if self.options.canonical {
+            // The tag depends on the elements, so they are written at the end
+            let elements = Some(Vec::with_capacity(len.unwrap_or(0)));
+            return Ok(SeqSerializer { ser: self, state: State::Empty, elements });
+        }
+
         match len {
             Some(0) => {
-                let bert_nil_tuple = get_bert_nil();
-                self.writer.write_all(bert_nil_tuple.as_slice())?;
-                Ok(SeqSerializer { ser: self, state: State::Empty })
+                self.write_bert_header(2, "nil")?;
+                Ok(SeqSerializer { ser: self, state: State::Empty, elements: None })
             }
             Some(list_length) => {
                 let mut header = vec![BertTag::List as u8];
                 header.write_i32::<BigEndian>(list_length as i32).unwrap();
                 self.writer.write_all(header.as_slice())?;
-                Ok(SeqSerializer { ser: self, state: State::First })
+                Ok(SeqSerializer { ser: self, state: State::First, elements: None })
             }
-            None => Ok(SeqSerializer { ser: self, state: State::Empty })
+            None => Ok(SeqSerializer { ser: self, state: State::Empty, elements: None })
         }
     }
 
@@ -666,13 +1070,21 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_tuple_struct(
         self, name: &'static str, len: usize
     ) -> Result<Self::SerializeTupleStruct> {
+        if name == IMPROPER_LIST_STRUCT_NAME {
+            // The last field is the list tail
+            let list_length = len.saturating_sub(1);
+            if list_length > 0 {
+                let mut header = vec![BertTag::List as u8];
+                header.write_u32::<BigEndian>(list_length as u32).unwrap();
+                self.writer.write_all(header.as_slice())?;
+            }
+            return Ok(TupleStructSerializer { ser: self });
+        }
+
         let tuple_size = len + 1; // include name of entity
-        let mut header = vec![BertTag::LargeTuple as u8];
-        header.write_i32::<BigEndian>(tuple_size as i32).unwrap();
-        self.writer.write_all(header.as_slice())?;
+        self.write_tuple_header(tuple_size)?;
 
-        let structure_name = get_atom(name);
-        self.writer.write_all(structure_name.as_slice())?;
+        self.write_atom(&name.to_lowercase())?;
 
         Ok(TupleStructSerializer { ser: self })
     }
@@ -682,33 +1094,37 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         self, name: &'static str, _variant_index: u32,
         variant: &'static str, len: usize
     ) -> Result<Self::SerializeTupleVariant> {
-        let mut header = vec![BertTag::LargeTuple as u8];
-        header.write_i32::<BigEndian>(2i32).unwrap();
-        self.writer.write_all(header.as_slice())?;
+        self.write_tuple_header(2)?;
+        self.write_atom(&name.to_lowercase())?;
 
-        let enum_name = get_atom(name);
-        self.writer.write_all(enum_name.as_slice())?;
+        let variant_length = len + 1;
+        self.write_tuple_header(variant_length)?;
 
-        let mut variant_header = vec![BertTag::LargeTuple as u8];
-        let variant_length = len as i32 + 1;
-        variant_header.write_i32::<BigEndian>(variant_length).unwrap();
-        self.writer.write_all(variant_header.as_slice())?;
-
-        let variant_name = get_atom(variant);
-        self.writer.write_all(variant_name.as_slice())?;
+        self.write_atom(&variant.to_lowercase())?;
 
         Ok(TupleVariantSerializer { ser: self })
     }
 
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        let header = vec![BertTag::SmallTuple as u8, 3u8];
-        let bert_atom = get_bert_atom();
-        let dict_atom = get_atom("dict");
+        if self.options.deterministic || len.is_none() {
+            // Entries are sorted, or their number which goes before them
+            // is not known yet, so they are written at the end
+            if self.options.map_encoding == MapEncoding::BertDict {
+                self.write_bert_header(3, "dict")?;
+            }
+            let entries = Some(Vec::with_capacity(len.unwrap_or(0)));
+            return Ok(MapSerializer { ser: self, state: State::Empty, entries, key: None });
+        }
 
-        self.writer.write_all(header.as_slice())?;
-        self.writer.write_all(bert_atom.as_slice())?;
-        self.writer.write_all(dict_atom.as_slice())?;
+        if let Some(length) = len && self.options.map_encoding == MapEncoding::Native {
+            let mut header = vec![BertTag::Map as u8];
+            header.write_u32::<BigEndian>(length as u32).unwrap();
+            self.writer.write_all(header.as_slice())?;
+            return Ok(MapSerializer { ser: self, state: State::First, entries: None, key: None });
+        }
+
+        self.write_bert_header(3, "dict")?;
 
         let mut list_header: Vec<u8> = vec![];
 
@@ -725,7 +1141,7 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         };
 
         self.writer.write_all(list_header.as_slice())?;
-        Ok(MapSerializer { ser: self, state })
+        Ok(MapSerializer { ser: self, state, entries: None, key: None })
     }
 
     #[inline]
@@ -734,31 +1150,61 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
     ) -> Result<Self::SerializeStruct> {
         match name {
             TIME_STRUCT_NAME => {
-                let header = vec![BertTag::SmallTuple as u8, len as u8];
-                let bert_atom = get_bert_atom();
-                let time_atom = get_atom("time");
-                self.writer.write_all(header.as_slice())?;
-                self.writer.write_all(bert_atom.as_slice())?;
-                self.writer.write_all(time_atom.as_slice())?;
+                self.write_bert_header(len as u8, "time")?;
                 Ok(StructSerializer::Bert { ser: self })
             }
             REGEX_STRUCT_NAME => {
-                let header = vec![BertTag::SmallTuple as u8, len as u8];
-                let bert_atom = get_bert_atom();
-                let regex_atom = get_atom("regex");
-                self.writer.write_all(header.as_slice())?;
-                self.writer.write_all(bert_atom.as_slice())?;
-                self.writer.write_all(regex_atom.as_slice())?;
+                self.write_bert_header(len as u8, "regex")?;
                 Ok(StructSerializer::Bert { ser: self })
             }
-            _ => {
-                let mut header = vec![BertTag::LargeTuple as u8];
-                let tuple_length = len as i32 + 1;
-                header.write_i32::<BigEndian>(tuple_length).unwrap();
+            PID_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::NewPid as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            LEGACY_PID_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::Pid as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            REFERENCE_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::NewerReference as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            PORT_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::NewPort as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            V4_PORT_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::V4Port as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            BITSTRING_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::BitBinary as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            EXPORT_FUN_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::Export as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            CLOSURE_STRUCT_NAME => {
+                self.writer.write_u8(BertTag::NewFun as u8)?;
+                Ok(StructSerializer::Extension { ser: self })
+            }
+            _ if self.options.map_encoding == MapEncoding::Native => {
+                if self.options.deterministic {
+                    let entries = Some(Vec::with_capacity(len));
+                    return Ok(StructSerializer::Map { ser: self, entries });
+                }
+
+                let mut header = vec![BertTag::Map as u8];
+                header.write_u32::<BigEndian>(len as u32).unwrap();
                 self.writer.write_all(header.as_slice())?;
+                Ok(StructSerializer::Map { ser: self, entries: None })
+            }
+            _ => {
+                let tuple_length = len + 1;
+                self.write_tuple_header(tuple_length)?;
 
-                let structure_name_atom = get_atom(name);
-                self.writer.write_all(structure_name_atom.as_slice())?;
+                self.write_atom(&name.to_lowercase())?;
                 Ok(StructSerializer::Regular { ser: self })
             }
         }
@@ -769,20 +1215,13 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
         self, name: &'static str, _variant_index: u32,
         variant: &'static str, len: usize
     ) -> Result<Self::SerializeStructVariant> {
-        let mut header = vec![BertTag::LargeTuple as u8];
-        header.write_i32::<BigEndian>(2i32).unwrap();
-        self.writer.write_all(header.as_slice())?;
-
-        let enum_name = get_atom(name);
-        self.writer.write_all(enum_name.as_slice())?;
+        self.write_tuple_header(2)?;
+        self.write_atom(&name.to_lowercase())?;
 
-        let mut variant_header = vec![BertTag::LargeTuple as u8];
-        let variant_length = len as i32 + 1;
-        variant_header.write_i32::<BigEndian>(variant_length).unwrap();
-        self.writer.write_all(variant_header.as_slice())?;
+        let variant_length = len + 1;
+        self.write_tuple_header(variant_length)?;
 
-        let variant_name = get_atom(variant);
-        self.writer.write_all(variant_name.as_slice())?;
+        self.write_atom(&variant.to_lowercase())?;
 
         Ok(StructVariantSerializer { ser: self })
     }
@@ -802,11 +1241,35 @@ where
 }
 
 
+/// Encode the passed value into a `[u8]` writer with the specified options
+#[inline]
+pub fn to_writer_with_options<W, T>(
+    writer: &mut W, value: &T, options: SerializerOptions
+) -> Result<()>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let mut ser = Serializer::with_options(writer, options);
+    value.serialize(&mut ser)?;
+    Ok(())
+}
+
+
 /// Encode the specified struct into a `[u8]` buffer
 #[inline]
 pub fn to_vec<T: ser::Serialize>(value: &T) -> Result<Vec<u8>> {
+    to_vec_with_options(value, SerializerOptions::default())
+}
+
+
+/// Encode the specified struct into a `[u8]` buffer with the specified options
+#[inline]
+pub fn to_vec_with_options<T: ser::Serialize>(
+    value: &T, options: SerializerOptions
+) -> Result<Vec<u8>> {
     let mut writer = Vec::with_capacity(128);
-    to_writer(&mut writer, value)?;
+    to_writer_with_options(&mut writer, value, options)?;
     Ok(writer)
 }
 
@@ -814,8 +1277,58 @@ pub fn to_vec<T: ser::Serialize>(value: &T) -> Result<Vec<u8>> {
 /// Convert passed value to a BERT representation
 #[inline]
 pub fn term_to_binary<T: ser::Serialize>(value: &T) -> Result<Vec<u8>> {
+    term_to_binary_with_options(value, SerializerOptions::default())
+}
+
+
+/// Convert passed value to a BERT representation with the specified options
+#[inline]
+pub fn term_to_binary_with_options<T: ser::Serialize>(
+    value: &T, options: SerializerOptions
+) -> Result<Vec<u8>> {
     let mut binary = vec![ETF_VERSION];
-    let data = to_vec(value)?;
+    let data = to_vec_with_options(value, options)?;
     binary.extend(data.iter());
     Ok(binary)
 }
+
+
+/// Convert passed value to a compressed BERT representation, like
+/// `term_to_binary(Term, [{compressed, Level}])` does. The level is in
+/// the range of 0..=9. When compression doesn't make the term smaller,
+/// the uncompressed representation is returned
+#[inline]
+pub fn term_to_binary_compressed<T: ser::Serialize>(
+    value: &T, level: u32
+) -> Result<Vec<u8>> {
+    term_to_binary_compressed_with_options(value, level, SerializerOptions::default())
+}
+
+
+/// Convert passed value to a compressed BERT representation with the
+/// specified options
+#[inline]
+pub fn term_to_binary_compressed_with_options<T: ser::Serialize>(
+    value: &T, level: u32, options: SerializerOptions
+) -> Result<Vec<u8>> {
+    if level > 9 {
+        let message = format!("compression level should be in 0..=9, got {}", level);
+        return Err(Error::Custom(message));
+    }
+
+    let data = to_vec_with_options(value, options)?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(level));
+    encoder.write_all(data.as_slice())?;
+    let compressed = encoder.finish()?;
+
+    // COMPRESSED tag and the uncompressed size take 5 bytes
+    let mut binary = vec![ETF_VERSION];
+    if compressed.len() + 5 < data.len() {
+        binary.push(BertTag::Compressed as u8);
+        binary.write_u32::<BigEndian>(data.len() as u32).unwrap();
+        binary.extend(compressed.iter());
+    } else {
+        binary.extend(data.iter());
+    }
+    Ok(binary)
+}
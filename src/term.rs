@@ -0,0 +1,599 @@
+//! Dynamic Erlang term
+//!
+//! `Term` holds any value of the external term format, so payloads which
+//! shape is not known in advance can be decoded without losing atoms,
+//! tuples or other Erlang specific types. `Term::from_bytes` and
+//! `Term::to_bytes` work with the binary format directly, while the serde
+//! implementations allow to put a `Term` into any other encoded value.
+use std::cmp::Ordering;
+use std::io::Read;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use num::bigint::{BigInt, Sign};
+use num::ToPrimitive;
+use serde::ser;
+
+use crate::deserializers::read_compressed_term;
+use crate::errors::{Error, Result};
+use crate::order::compare;
+use crate::serializers::{MinorVersion, SerializerOptions, to_vec_with_options};
+use crate::types::{
+    ETF_VERSION, BertTag, BertPid, BertReference, BertPort, BertBitString,
+    BertExportFun, BertClosure, PidEncoding
+};
+use crate::utils::{get_small_utf8_atom, get_integer, get_bignum, get_nil};
+
+
+/// Containers which can be nested into each other while decoding, so the
+/// hostile input can't overflow the stack
+const MAX_TERM_DEPTH: usize = 128;
+
+
+/// Any Erlang term. Terms are equal when Erlang considers them exactly
+/// equal (`=:=`), so a list of bytes is equal to the same `String` and map
+/// entries are compared regardless of their order
+#[derive(Debug, Clone)]
+pub enum Term {
+    Atom(String),
+    /// Integer which fits into i64, regardless of the tag it was encoded with
+    Integer(i64),
+    /// Integer outside of the i64 range
+    BigInteger(BigInt),
+    Float(f64),
+    Binary(Vec<u8>),
+    BitString(BertBitString),
+    /// List of bytes, encoded as STRING_EXT (`"abc"` in Erlang)
+    String(Vec<u8>),
+    /// Proper list. The empty list is encoded as NIL_EXT and lists of bytes
+    /// as STRING_EXT
+    List(Vec<Term>),
+    /// List elements and the tail other than nil, like `[a, b | c]`
+    ImproperList(Vec<Term>, Box<Term>),
+    Tuple(Vec<Term>),
+    /// Map entries in the order they were encoded
+    Map(Vec<(Term, Term)>),
+    Pid(BertPid),
+    Reference(BertReference),
+    Port(BertPort),
+    ExportFun(BertExportFun),
+    Closure(BertClosure),
+}
+
+
+impl Term {
+    /// Decode the term from the external term format, starting with the
+    /// version number. Compressed terms are inflated
+    pub fn from_bytes(data: &[u8]) -> Result<Term> {
+        let mut reader = data;
+        if reader.read_u8()? != ETF_VERSION {
+            return Err(Error::InvalidVersionNumber);
+        }
+
+        let term = match reader.read_u8()? {
+            80 => {
+                let data = read_compressed_term(&mut reader)?;
+                decode_term(&data)?
+            }
+            tag => read_term(&mut reader, tag, &[])?,
+        };
+        if !reader.is_empty() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(term)
+    }
+
+    /// Encode the term with the version number. The tags are picked the
+    /// same way as `erlang:term_to_binary` does since OTP 26, so the bytes
+    /// produced by it are reproduced exactly. Atoms which older releases
+    /// wrote as ATOM_EXT come back with the UTF-8 tags
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut binary = vec![ETF_VERSION];
+        self.write(&mut binary)?;
+        Ok(binary)
+    }
+
+    /// Compare the terms in the Erlang term order, where numbers are
+    /// compared by their values (so `1` is equal to `1.0`)
+    pub fn compare(&self, other: &Term) -> Ordering {
+        compare(self, other, false)
+    }
+
+    /// Append the encoded term, without the version number
+    pub(crate) fn write(&self, binary: &mut Vec<u8>) -> Result<()> {
+        match self {
+            Term::Atom(name) => write_atom(binary, name)?,
+            Term::Integer(value) => binary.extend(get_integer(*value as i128)),
+            Term::BigInteger(value) => binary.extend(get_bignum(value)),
+            Term::Float(value) => {
+                binary.push(BertTag::NewFloat as u8);
+                binary.write_f64::<BigEndian>(*value)?;
+            }
+            Term::Binary(bytes) => {
+                binary.push(BertTag::Binary as u8);
+                binary.write_u32::<BigEndian>(bytes.len() as u32)?;
+                binary.extend(bytes);
+            }
+            Term::BitString(bitstring) => write_struct(binary, bitstring)?,
+            Term::String(bytes) if bytes.len() <= u16::MAX as usize => {
+                binary.push(BertTag::String as u8);
+                binary.write_u16::<BigEndian>(bytes.len() as u16)?;
+                binary.extend(bytes);
+            }
+            Term::String(bytes) => {
+                // STRING_EXT can't hold more than 65535 bytes
+                binary.push(BertTag::List as u8);
+                binary.write_u32::<BigEndian>(bytes.len() as u32)?;
+                for byte in bytes {
+                    binary.extend([BertTag::SmallInteger as u8, *byte]);
+                }
+                binary.extend(get_nil());
+            }
+            Term::List(elements) if elements.is_empty() => binary.extend(get_nil()),
+            Term::List(elements) => match list_bytes(elements) {
+                // Erlang writes lists of bytes as STRING_EXT
+                Some(bytes) if bytes.len() <= u16::MAX as usize => {
+                    binary.push(BertTag::String as u8);
+                    binary.write_u16::<BigEndian>(bytes.len() as u16)?;
+                    binary.extend(bytes);
+                }
+                _ => {
+                    binary.push(BertTag::List as u8);
+                    binary.write_u32::<BigEndian>(elements.len() as u32)?;
+                    for element in elements {
+                        element.write(binary)?;
+                    }
+                    binary.extend(get_nil());
+                }
+            },
+            Term::ImproperList(elements, tail) => {
+                if !elements.is_empty() {
+                    binary.push(BertTag::List as u8);
+                    binary.write_u32::<BigEndian>(elements.len() as u32)?;
+                    for element in elements {
+                        element.write(binary)?;
+                    }
+                }
+                tail.write(binary)?;
+            }
+            Term::Tuple(elements) => {
+                match u8::try_from(elements.len()) {
+                    Ok(arity) => binary.extend([BertTag::SmallTuple as u8, arity]),
+                    Err(_) => {
+                        binary.push(BertTag::LargeTuple as u8);
+                        binary.write_u32::<BigEndian>(elements.len() as u32)?;
+                    }
+                }
+                for element in elements {
+                    element.write(binary)?;
+                }
+            }
+            Term::Map(entries) => {
+                binary.push(BertTag::Map as u8);
+                binary.write_u32::<BigEndian>(entries.len() as u32)?;
+                for (key, value) in entries {
+                    key.write(binary)?;
+                    value.write(binary)?;
+                }
+            }
+            Term::Pid(pid) => write_struct(binary, pid)?,
+            Term::Reference(reference) => write_struct(binary, reference)?,
+            Term::Port(port) => write_struct(binary, port)?,
+            Term::ExportFun(fun) => write_struct(binary, fun)?,
+            Term::Closure(closure) => write_struct(binary, closure)?,
+        }
+        Ok(())
+    }
+}
+
+
+macro_rules! term_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Term {
+                fn from(value: $ty) -> Term {
+                    read_integer(BigInt::from(value))
+                }
+            }
+        )*
+    }
+}
+
+term_from_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+
+impl From<BigInt> for Term {
+    fn from(value: BigInt) -> Term {
+        read_integer(value)
+    }
+}
+
+
+impl From<f32> for Term {
+    fn from(value: f32) -> Term {
+        Term::Float(value as f64)
+    }
+}
+
+
+impl From<f64> for Term {
+    fn from(value: f64) -> Term {
+        Term::Float(value)
+    }
+}
+
+
+/// Booleans are the `true` and `false` atoms
+impl From<bool> for Term {
+    fn from(value: bool) -> Term {
+        Term::Atom(value.to_string())
+    }
+}
+
+
+/// Strings are lists of bytes, the same as the serializer writes them
+impl From<&str> for Term {
+    fn from(value: &str) -> Term {
+        Term::String(value.as_bytes().to_vec())
+    }
+}
+
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Term) -> bool {
+        compare(self, other, true) == Ordering::Equal
+    }
+}
+
+
+impl From<String> for Term {
+    fn from(value: String) -> Term {
+        Term::String(value.into_bytes())
+    }
+}
+
+
+impl From<&[u8]> for Term {
+    fn from(value: &[u8]) -> Term {
+        Term::Binary(value.to_vec())
+    }
+}
+
+
+impl From<Vec<u8>> for Term {
+    fn from(value: Vec<u8>) -> Term {
+        Term::Binary(value)
+    }
+}
+
+
+impl From<BertPid> for Term {
+    fn from(value: BertPid) -> Term {
+        Term::Pid(value)
+    }
+}
+
+
+impl From<BertReference> for Term {
+    fn from(value: BertReference) -> Term {
+        Term::Reference(value)
+    }
+}
+
+
+impl From<BertPort> for Term {
+    fn from(value: BertPort) -> Term {
+        Term::Port(value)
+    }
+}
+
+
+impl From<BertBitString> for Term {
+    fn from(value: BertBitString) -> Term {
+        Term::BitString(value)
+    }
+}
+
+
+impl From<BertExportFun> for Term {
+    fn from(value: BertExportFun) -> Term {
+        Term::ExportFun(value)
+    }
+}
+
+
+/// Write the atom with the same tag as Erlang does since OTP 26:
+/// SMALL_ATOM_UTF8_EXT, or ATOM_UTF8_EXT for names longer than 255 bytes
+fn write_atom(binary: &mut Vec<u8>, name: &str) -> Result<()> {
+    binary.extend(get_small_utf8_atom(name)?);
+    Ok(())
+}
+
+
+/// Bytes of the list which elements are all integers in 0..=255
+fn list_bytes(elements: &[Term]) -> Option<Vec<u8>> {
+    elements.iter()
+        .map(|element| match element {
+            Term::Integer(value) => u8::try_from(*value).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+
+/// Write the Erlang specific type through its `Serialize` implementation
+fn write_struct<T: ser::Serialize>(binary: &mut Vec<u8>, value: &T) -> Result<()> {
+    let options = SerializerOptions {
+        canonical: true,
+        minor_version: MinorVersion::V2,
+        ..SerializerOptions::default()
+    };
+    binary.extend(to_vec_with_options(value, options)?);
+    Ok(())
+}
+
+
+/// Decode the term without the version number, which takes the whole data
+pub(crate) fn decode_term(data: &[u8]) -> Result<Term> {
+    let mut reader = data;
+    let tag = reader.read_u8()?;
+    let term = read_term(&mut reader, tag, &[])?;
+    if !reader.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(term)
+}
+
+
+/// Read the bytes as they arrive, so the length taken from the input
+/// doesn't allocate more memory than the input has
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(length.min(4096));
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != length {
+        return Err(Error::EndOfStream);
+    }
+    Ok(bytes)
+}
+
+
+fn read_terms<R: Read>(reader: &mut R, count: usize, atoms: &[String], depth: usize) -> Result<Vec<Term>> {
+    let mut terms = Vec::with_capacity(count.min(4096));
+    for _ in 0..count {
+        let tag = reader.read_u8()?;
+        terms.push(read_nested_term(reader, tag, atoms, depth)?);
+    }
+    Ok(terms)
+}
+
+
+fn read_atom<R: Read>(reader: &mut R, atoms: &[String], depth: usize) -> Result<String> {
+    let tag = reader.read_u8()?;
+    read_atom_value(reader, tag, atoms, depth)
+}
+
+
+/// Read an atom after its tag has been consumed
+fn read_atom_value<R: Read>(
+    reader: &mut R, tag: u8, atoms: &[String], depth: usize
+) -> Result<String> {
+    match read_nested_term(reader, tag, atoms, depth)? {
+        Term::Atom(name) => Ok(name),
+        _ => Err(Error::InvalidTag),
+    }
+}
+
+
+/// Read the term and return it as it was encoded
+fn read_raw_term(reader: &mut &[u8], atoms: &[String], depth: usize) -> Result<Vec<u8>> {
+    let start = *reader;
+    let tag = reader.read_u8()?;
+    read_nested_term(reader, tag, atoms, depth)?;
+    Ok(start[..start.len() - reader.len()].to_vec())
+}
+
+
+fn read_integer(value: BigInt) -> Term {
+    match value.to_i64() {
+        Some(value) => Term::Integer(value),
+        None => Term::BigInteger(value),
+    }
+}
+
+
+/// Read the term after its tag has been consumed. `atoms` resolve
+/// ATOM_CACHE_REF
+pub(crate) fn read_term<R: Read>(reader: &mut R, tag: u8, atoms: &[String]) -> Result<Term> {
+    read_nested_term(reader, tag, atoms, 0)
+}
+
+
+/// Read the term which is nested into `depth` containers
+fn read_nested_term<R: Read>(reader: &mut R, tag: u8, atoms: &[String], depth: usize) -> Result<Term> {
+    if depth > MAX_TERM_DEPTH {
+        return Err(Error::NestingTooDeep);
+    }
+    let nested = depth + 1;
+    let term = match tag {
+        104 => {
+            let arity = reader.read_u8()? as usize;
+            Term::Tuple(read_terms(reader, arity, atoms, nested)?)
+        }
+        105 => {
+            let arity = reader.read_u32::<BigEndian>()? as usize;
+            Term::Tuple(read_terms(reader, arity, atoms, nested)?)
+        }
+        116 => {
+            let arity = reader.read_u32::<BigEndian>()? as usize;
+            let mut entries = Vec::with_capacity(arity.min(4096));
+            for _ in 0..arity {
+                let mut pair = read_terms(reader, 2, atoms, nested)?;
+                let value = pair.pop().unwrap();
+                let key = pair.pop().unwrap();
+                entries.push((key, value));
+            }
+            Term::Map(entries)
+        }
+        106 => Term::List(vec![]),
+        108 => {
+            let length = reader.read_u32::<BigEndian>()? as usize;
+            let elements = read_terms(reader, length, atoms, nested)?;
+            match read_terms(reader, 1, atoms, nested)?.pop().unwrap() {
+                Term::List(tail) if tail.is_empty() => Term::List(elements),
+                tail => Term::ImproperList(elements, Box::new(tail)),
+            }
+        }
+        _ => read_leaf_term(reader, tag, atoms, nested)?,
+    };
+    Ok(term)
+}
+
+
+/// Read the term other than a tuple, a list or a map. It's kept apart to
+/// take less of the stack for every nested container
+#[inline(never)]
+fn read_leaf_term<R: Read>(reader: &mut R, tag: u8, atoms: &[String], nested: usize) -> Result<Term> {
+    let term = match tag {
+        97 => Term::Integer(reader.read_u8()? as i64),
+        98 => Term::Integer(reader.read_i32::<BigEndian>()? as i64),
+        110 | 111 => {
+            let length = match tag {
+                110 => reader.read_u8()? as usize,
+                _ => reader.read_u32::<BigEndian>()? as usize,
+            };
+            let sign = match reader.read_u8()? {
+                0 => Sign::Plus,
+                _ => Sign::Minus,
+            };
+            read_integer(BigInt::from_bytes_le(sign, &read_bytes(reader, length)?))
+        }
+        70 => Term::Float(reader.read_f64::<BigEndian>()?),
+        99 => {
+            let bytes = read_bytes(reader, 31)?;
+            let text = String::from_utf8(bytes)?;
+            Term::Float(text.trim_end_matches('\0').parse()?)
+        }
+        82 => {
+            let index = reader.read_u8()? as usize;
+            let atom = atoms.get(index).ok_or_else(|| {
+                Error::Custom(format!("atom cache reference {} is out of range", index))
+            })?;
+            Term::Atom(atom.clone())
+        }
+        100 | 115 => {
+            // Both tags are limited to Latin-1 characters
+            let length = match tag {
+                100 => reader.read_u16::<BigEndian>()? as usize,
+                _ => reader.read_u8()? as usize,
+            };
+            let bytes = read_bytes(reader, length)?;
+            Term::Atom(bytes.iter().map(|&byte| byte as char).collect())
+        }
+        118 | 119 => {
+            let length = match tag {
+                118 => reader.read_u16::<BigEndian>()? as usize,
+                _ => reader.read_u8()? as usize,
+            };
+            Term::Atom(String::from_utf8(read_bytes(reader, length)?)?)
+        }
+        101 => {
+            let node = read_atom(reader, atoms, nested)?;
+            let id = reader.read_u32::<BigEndian>()?;
+            let creation = reader.read_u8()? as u32;
+            Term::Reference(BertReference::new(&node, creation, vec![id]))
+        }
+        90 | 114 => {
+            let length = reader.read_u16::<BigEndian>()? as usize;
+            let node = read_atom(reader, atoms, nested)?;
+            let creation = match tag {
+                90 => reader.read_u32::<BigEndian>()?,
+                _ => reader.read_u8()? as u32,
+            };
+            let id = (0..length)
+                .map(|_| reader.read_u32::<BigEndian>())
+                .collect::<std::io::Result<Vec<u32>>>()?;
+            Term::Reference(BertReference::new(&node, creation, id))
+        }
+        89 | 102 | 120 => {
+            let node = read_atom(reader, atoms, nested)?;
+            let id = match tag {
+                120 => reader.read_u64::<BigEndian>()?,
+                _ => reader.read_u32::<BigEndian>()? as u64,
+            };
+            let creation = match tag {
+                102 => reader.read_u8()? as u32,
+                _ => reader.read_u32::<BigEndian>()?,
+            };
+            Term::Port(BertPort::new(&node, id, creation))
+        }
+        88 | 103 => {
+            let node_tag = reader.read_u8()?;
+            let node = read_atom_value(reader, node_tag, atoms, nested)?;
+            let id = reader.read_u32::<BigEndian>()?;
+            let serial = reader.read_u32::<BigEndian>()?;
+            let creation = match tag {
+                103 => reader.read_u8()? as u32,
+                _ => reader.read_u32::<BigEndian>()?,
+            };
+            let mut pid = BertPid::new(&node, id, serial, creation);
+            pid.encoding = Some(PidEncoding {
+                tag: BertTag::try_from(tag)?,
+                node_tag: BertTag::try_from(node_tag)?,
+            });
+            Term::Pid(pid)
+        }
+        113 => {
+            let module = read_atom(reader, atoms, nested)?;
+            let function = read_atom(reader, atoms, nested)?;
+            let arity = match read_terms(reader, 1, atoms, nested)?.pop() {
+                Some(Term::Integer(arity)) => u8::try_from(arity).map_err(|_| {
+                    Error::IntegerOverflow("u8")
+                })?,
+                _ => return Err(Error::InvalidTag),
+            };
+            Term::ExportFun(BertExportFun::new(&module, &function, arity))
+        }
+        112 => {
+            // The size includes the size field itself
+            let size = reader.read_u32::<BigEndian>()? as usize;
+            let length = size.checked_sub(4).ok_or(Error::EndOfStream)?;
+            let body = read_bytes(reader, length)?;
+            let mut body = body.as_slice();
+
+            let arity = body.read_u8()?;
+            let mut uniq = [0u8; 16];
+            body.read_exact(&mut uniq)?;
+            let index = body.read_u32::<BigEndian>()?;
+            let num_free = body.read_u32::<BigEndian>()? as usize;
+            let module = read_raw_term(&mut body, atoms, nested)?;
+            let old_index = read_raw_term(&mut body, atoms, nested)?;
+            let old_uniq = read_raw_term(&mut body, atoms, nested)?;
+            let pid = read_raw_term(&mut body, atoms, nested)?;
+            let free_vars = (0..num_free)
+                .map(|_| read_raw_term(&mut body, atoms, nested))
+                .collect::<Result<Vec<Vec<u8>>>>()?;
+            if !body.is_empty() {
+                return Err(Error::TrailingBytes);
+            }
+
+            Term::Closure(BertClosure {
+                arity, uniq, index, module, old_index, old_uniq, pid, free_vars
+            })
+        }
+        107 => {
+            let length = reader.read_u16::<BigEndian>()? as usize;
+            Term::String(read_bytes(reader, length)?)
+        }
+        109 => {
+            let length = reader.read_u32::<BigEndian>()? as usize;
+            Term::Binary(read_bytes(reader, length)?)
+        }
+        77 => {
+            let length = reader.read_u32::<BigEndian>()? as usize;
+            let bits_in_last_byte = reader.read_u8()?;
+            let bytes = read_bytes(reader, length)?;
+            Term::BitString(BertBitString::try_new(bytes, bits_in_last_byte)?)
+        }
+        _ => return Err(Error::InvalidTag),
+    };
+    Ok(term)
+}
@@ -0,0 +1,168 @@
+// Macros for writing terms in the Erlang syntax, mostly to build expected
+// values in tests without assembling the bytes by hand
+
+
+/// Part of the binary in the `<<...>>` syntax of the `term!` macro
+#[doc(hidden)]
+pub trait BinarySegment {
+    fn extend_binary(self, binary: &mut Vec<u8>);
+}
+
+
+impl BinarySegment for u8 {
+    fn extend_binary(self, binary: &mut Vec<u8>) {
+        binary.push(self);
+    }
+}
+
+
+impl BinarySegment for &str {
+    fn extend_binary(self, binary: &mut Vec<u8>) {
+        binary.extend(self.as_bytes());
+    }
+}
+
+
+impl BinarySegment for &String {
+    fn extend_binary(self, binary: &mut Vec<u8>) {
+        binary.extend(self.as_bytes());
+    }
+}
+
+
+impl BinarySegment for &[u8] {
+    fn extend_binary(self, binary: &mut Vec<u8>) {
+        binary.extend(self);
+    }
+}
+
+
+impl<const N: usize> BinarySegment for &[u8; N] {
+    fn extend_binary(self, binary: &mut Vec<u8>) {
+        binary.extend(self);
+    }
+}
+
+
+impl BinarySegment for &Vec<u8> {
+    fn extend_binary(self, binary: &mut Vec<u8>) {
+        binary.extend(self);
+    }
+}
+
+
+/// Build a `Term` from the Erlang syntax:
+///
+/// - `ok` is an atom, `true` and `false` are atoms too
+/// - `1`, `-1` and `1.5` are numbers
+/// - `"abc"` is a string (a list of bytes, as the serializer writes it)
+/// - `<<"abc", 1, 2>>` is a binary made of strings, bytes and byte slices
+/// - `{a, b}` is a tuple, `[a, b]` is a list and `[a | b]` is an improper list
+/// - `#{a => 1}` is a map
+/// - `(expr)` is any Rust expression which converts `Into<Term>`, or a
+///   byte slice inside of a binary
+#[macro_export]
+macro_rules! term {
+    // Parse the term at the start of the tokens and pass it to `@$next`
+    (@head $next:ident $state:tt << >> $($rest:tt)*) => {
+        $crate::term!(@$next $state ($crate::Term::Binary(::std::vec::Vec::new())) $($rest)*)
+    };
+    (@head $next:ident $state:tt << $($segment:tt),+ >> $($rest:tt)*) => {
+        $crate::term!(@$next $state ($crate::term!(<< $($segment),+ >>)) $($rest)*)
+    };
+    (@head $next:ident $state:tt # { $($entries:tt)* } $($rest:tt)*) => {
+        $crate::term!(@$next $state ($crate::term!(#{ $($entries)* })) $($rest)*)
+    };
+    (@head $next:ident $state:tt - $number:literal $($rest:tt)*) => {
+        $crate::term!(@$next $state ($crate::Term::from(-$number)) $($rest)*)
+    };
+    (@head $next:ident $state:tt $term:tt $($rest:tt)*) => {
+        $crate::term!(@$next $state ($crate::term!($term)) $($rest)*)
+    };
+
+    // Tuple and list elements
+    (@elements [$kind:ident $($element:expr,)*]) => {
+        $crate::term!(@finish $kind $($element,)*)
+    };
+    (@elements $state:tt $($rest:tt)+) => {
+        $crate::term!(@head element $state $($rest)+)
+    };
+    (@element [$kind:ident $($element:expr,)*] ($last:expr)) => {
+        $crate::term!(@finish $kind $($element,)* $last,)
+    };
+    (@element [$kind:ident $($element:expr,)*] ($last:expr) , $($rest:tt)*) => {
+        $crate::term!(@elements [$kind $($element,)* $last,] $($rest)*)
+    };
+    (@element [list $($element:expr,)*] ($last:expr) | $($tail:tt)+) => {
+        $crate::Term::ImproperList(
+            ::std::vec![$($element,)* $last],
+            ::std::boxed::Box::new($crate::term!($($tail)+))
+        )
+    };
+    (@finish tuple $($element:expr,)*) => {
+        $crate::Term::Tuple(::std::vec![$($element),*])
+    };
+    (@finish list $($element:expr,)*) => {
+        $crate::Term::List(::std::vec![$($element),*])
+    };
+
+    // Map entries
+    (@entries [$($entry:expr,)*]) => {
+        $crate::Term::Map(::std::vec![$($entry),*])
+    };
+    (@entries $state:tt $($rest:tt)+) => {
+        $crate::term!(@head key $state $($rest)+)
+    };
+    (@key [$($entry:expr,)*] ($key:expr) => $($rest:tt)+) => {
+        $crate::term!(@head value ([$($entry,)*] $key) $($rest)+)
+    };
+    (@value ([$($entry:expr,)*] $key:expr) ($value:expr)) => {
+        $crate::Term::Map(::std::vec![$($entry,)* ($key, $value)])
+    };
+    (@value ([$($entry:expr,)*] $key:expr) ($value:expr) , $($rest:tt)*) => {
+        $crate::term!(@entries [$($entry,)* ($key, $value),] $($rest)*)
+    };
+
+    ({ $($elements:tt)* }) => {
+        $crate::term!(@elements [tuple] $($elements)*)
+    };
+    ([ $($elements:tt)* ]) => {
+        $crate::term!(@elements [list] $($elements)*)
+    };
+    (# { $($entries:tt)* }) => {
+        $crate::term!(@entries [] $($entries)*)
+    };
+    (<< >>) => {
+        $crate::Term::Binary(::std::vec::Vec::new())
+    };
+    (<< $($segment:tt),+ >>) => {{
+        let mut binary = ::std::vec::Vec::new();
+        $(
+            #[allow(unused_parens)]
+            $crate::BinarySegment::extend_binary($segment, &mut binary);
+        )+
+        $crate::Term::Binary(binary)
+    }};
+    (- $number:literal) => {
+        $crate::Term::from(-$number)
+    };
+    (( $value:expr )) => {
+        $crate::Term::from($value)
+    };
+    ($atom:ident) => {
+        $crate::Term::Atom(::std::string::String::from(stringify!($atom)))
+    };
+    ($value:literal) => {
+        $crate::Term::from($value)
+    };
+}
+
+
+/// Encode the term written in the Erlang syntax of the `term!` macro,
+/// with the version number. Returns `Result<Vec<u8>>`
+#[macro_export]
+macro_rules! term_bytes {
+    ($($term:tt)+) => {
+        $crate::term!($($term)+).to_bytes()
+    };
+}
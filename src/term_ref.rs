@@ -0,0 +1,438 @@
+//! Borrowed view over encoded terms
+//!
+//! `TermRef` points into the encoded data and decodes only the parts that
+//! are accessed: getting an element of a tuple skips over the preceding
+//! elements without decoding them, while atoms, binaries and strings are
+//! returned as slices of the original data.
+use num::bigint::{BigInt, Sign};
+use num::ToPrimitive;
+
+use crate::errors::{Error, Result};
+use crate::term::{Term, decode_term};
+use crate::types::{ETF_VERSION, BertTag};
+use crate::utils::to_latin1;
+
+
+/// Encoded term, borrowed from the data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermRef<'a> {
+    /// Data starting with the tag of the term
+    data: &'a [u8],
+}
+
+
+impl<'a> TermRef<'a> {
+    /// View over the encoded term, starting with the version number.
+    /// Compressed terms can't be borrowed and are rejected
+    pub fn new(data: &'a [u8]) -> Result<TermRef<'a>> {
+        match data {
+            [ETF_VERSION, 80, ..] => {
+                Err(Error::Custom("compressed term can't be borrowed".to_string()))
+            }
+            [ETF_VERSION, _, ..] => Ok(TermRef { data: &data[1..] }),
+            [ETF_VERSION] => Err(Error::EndOfStream),
+            _ => Err(Error::InvalidVersionNumber),
+        }
+    }
+
+    fn tag_byte(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// Skipper positioned after the tag
+    fn body(&self) -> TermSkipper<'a> {
+        let mut skipper = TermSkipper::new(self.data);
+        skipper.position = 1;
+        skipper
+    }
+
+    /// Tag of the term
+    pub fn tag(&self) -> Result<BertTag> {
+        BertTag::try_from(self.tag_byte())
+    }
+
+    /// Encoded term, without the version number
+    pub fn as_bytes(&self) -> Result<&'a [u8]> {
+        TermSkipper::new(self.data).read_raw_term()
+    }
+
+    /// Decode the whole term
+    pub fn to_term(&self) -> Result<Term> {
+        decode_term(self.as_bytes()?)
+    }
+
+    /// Text of the atom: Latin-1 for ATOM_EXT and SMALL_ATOM_EXT, UTF-8
+    /// for the other atom tags
+    pub fn as_atom(&self) -> Result<&'a [u8]> {
+        let mut body = self.body();
+        let length = match self.tag_byte() {
+            100 | 118 => body.read_u16()? as usize,
+            115 | 119 => body.read_u8()? as usize,
+            _ => return Err(Error::InvalidTag),
+        };
+        body.read_slice(length)
+    }
+
+    /// Check whether the term is the atom with the given name
+    pub fn is_atom(&self, name: &str) -> bool {
+        match (self.tag_byte(), self.as_atom()) {
+            (100 | 115, Ok(text)) => to_latin1(name).is_some_and(|latin1| latin1 == text),
+            (_, Ok(text)) => name.as_bytes() == text,
+            (_, Err(_)) => false,
+        }
+    }
+
+    /// Bytes of BINARY_EXT
+    pub fn as_binary(&self) -> Result<&'a [u8]> {
+        if self.tag_byte() != BertTag::Binary as u8 {
+            return Err(Error::InvalidTag);
+        }
+        let mut body = self.body();
+        let length = body.read_u32()? as usize;
+        body.read_slice(length)
+    }
+
+    /// Bytes of STRING_EXT, which is a list of small integers
+    pub fn as_string(&self) -> Result<&'a [u8]> {
+        if self.tag_byte() != BertTag::String as u8 {
+            return Err(Error::InvalidTag);
+        }
+        let mut body = self.body();
+        let length = body.read_u16()? as usize;
+        body.read_slice(length)
+    }
+
+    /// Value of the integer, when it fits into i64
+    pub fn as_integer(&self) -> Result<i64> {
+        let mut body = self.body();
+        match self.tag_byte() {
+            97 => Ok(body.read_u8()? as i64),
+            98 => Ok(body.read_u32()? as i32 as i64),
+            110 | 111 => {
+                let length = match self.tag_byte() {
+                    110 => body.read_u8()? as usize,
+                    _ => body.read_u32()? as usize,
+                };
+                let sign = match body.read_u8()? {
+                    0 => Sign::Plus,
+                    _ => Sign::Minus,
+                };
+                BigInt::from_bytes_le(sign, body.read_slice(length)?)
+                    .to_i64()
+                    .ok_or(Error::IntegerOverflow("i64"))
+            }
+            _ => Err(Error::InvalidTag),
+        }
+    }
+
+    /// Value of NEW_FLOAT_EXT or FLOAT_EXT
+    pub fn as_float(&self) -> Result<f64> {
+        match self.tag_byte() {
+            70 | 99 => match self.to_term()? {
+                Term::Float(value) => Ok(value),
+                _ => Err(Error::InvalidTag),
+            },
+            _ => Err(Error::InvalidTag),
+        }
+    }
+
+    /// Number of elements of the tuple or the list (not counting the
+    /// tail), or number of entries of the map
+    pub fn len(&self) -> Result<usize> {
+        let mut body = self.body();
+        match self.tag_byte() {
+            104 => Ok(body.read_u8()? as usize),
+            105 | 108 | 116 => Ok(body.read_u32()? as usize),
+            107 => Ok(body.read_u16()? as usize),
+            106 => Ok(0),
+            _ => Err(Error::InvalidTag),
+        }
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Elements of the tuple or the list. The list tail is not included.
+    /// Bytes of STRING_EXT are yielded as SMALL_INTEGER_EXT
+    pub fn elements(&self) -> Result<Elements<'a>> {
+        let mut body = self.body();
+        let remaining = match self.tag_byte() {
+            104 => body.read_u8()? as usize,
+            105 | 108 => body.read_u32()? as usize,
+            107 => body.read_u16()? as usize,
+            106 => 0,
+            _ => return Err(Error::InvalidTag),
+        };
+        let bytes = self.tag_byte() == BertTag::String as u8;
+        Ok(Elements { skipper: body, remaining, bytes })
+    }
+
+    /// Element of the tuple or the list at the index. Only the preceding
+    /// elements are walked over
+    pub fn element(&self, index: usize) -> Result<Option<TermRef<'a>>> {
+        let mut elements = self.elements()?;
+        if index >= elements.remaining {
+            return Ok(None);
+        }
+        if elements.bytes {
+            elements.skipper.skip(index)?;
+        } else {
+            elements.skipper.skip_terms(index)?;
+        }
+        elements.remaining -= index;
+        elements.next().transpose()
+    }
+
+    /// Tail of the list: nil for proper lists
+    pub fn tail(&self) -> Result<TermRef<'a>> {
+        match self.tag_byte() {
+            106 | 107 => Ok(TermRef { data: &[BertTag::Nil as u8] }),
+            108 => {
+                let mut elements = self.elements()?;
+                elements.skipper.skip_terms(elements.remaining)?;
+                let tail = elements.skipper.read_raw_term()?;
+                Ok(TermRef { data: tail })
+            }
+            _ => Err(Error::InvalidTag),
+        }
+    }
+
+    /// Key and value pairs of the map
+    pub fn entries(&self) -> Result<Entries<'a>> {
+        if self.tag_byte() != BertTag::Map as u8 {
+            return Err(Error::InvalidTag);
+        }
+        let mut body = self.body();
+        let remaining = body.read_u32()? as usize;
+        Ok(Entries { skipper: body, remaining })
+    }
+
+    /// Value of the map with the given key. Keys are compared without
+    /// decoding, by their encoding as `Term::to_bytes` writes it, which is
+    /// how Erlang writes terms since OTP 26. Atoms match with any atom tag
+    pub fn get(&self, key: &Term) -> Result<Option<TermRef<'a>>> {
+        let mut encoded = vec![];
+        key.write(&mut encoded)?;
+
+        for entry in self.entries()? {
+            let (entry_key, value) = entry?;
+            let matches = match key {
+                Term::Atom(name) => entry_key.is_atom(name),
+                _ => entry_key.as_bytes()? == encoded.as_slice(),
+            };
+            if matches {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+
+/// SMALL_INTEGER_EXT of every byte, for the elements of STRING_EXT
+static SMALL_INTEGERS: [[u8; 2]; 256] = {
+    let mut integers = [[0; 2]; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        integers[byte] = [BertTag::SmallInteger as u8, byte as u8];
+        byte += 1;
+    }
+    integers
+};
+
+
+/// Iterator over elements of the tuple or the list
+pub struct Elements<'a> {
+    skipper: TermSkipper<'a>,
+    remaining: usize,
+    /// Elements are the bytes of STRING_EXT
+    bytes: bool,
+}
+
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Result<TermRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        if self.bytes {
+            let byte = self.skipper.read_u8();
+            return Some(byte.map(|byte| TermRef { data: &SMALL_INTEGERS[byte as usize] }));
+        }
+        Some(self.skipper.read_raw_term().map(|data| TermRef { data }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+
+/// Iterator over entries of the map
+pub struct Entries<'a> {
+    skipper: TermSkipper<'a>,
+    remaining: usize,
+}
+
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<(TermRef<'a>, TermRef<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let mut read_entry = || {
+            let key = self.skipper.read_raw_term()?;
+            let value = self.skipper.read_raw_term()?;
+            Ok((TermRef { data: key }, TermRef { data: value }))
+        };
+        Some(read_entry())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+
+/// Walks over encoded terms in a slice without decoding them, to find
+/// where each term ends
+pub(crate) struct TermSkipper<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> TermSkipper<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        TermSkipper { data, position: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    pub(crate) fn read_slice(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.data.len() - self.position < length {
+            return Err(Error::EndOfStream);
+        }
+        let slice = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(slice)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    pub(crate) fn read_u16(&mut self) -> Result<u16> {
+        let bytes = self.read_slice(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_slice(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read the next term, including its tag, as raw bytes
+    pub(crate) fn read_raw_term(&mut self) -> Result<&'a [u8]> {
+        let start = self.position;
+        self.skip_term()?;
+        Ok(&self.data[start..self.position])
+    }
+
+    pub(crate) fn skip(&mut self, length: usize) -> Result<()> {
+        self.read_slice(length).map(|_| ())
+    }
+
+    pub(crate) fn skip_terms(&mut self, count: usize) -> Result<()> {
+        for _ in 0..count {
+            self.skip_term()?;
+        }
+        Ok(())
+    }
+
+    /// Skip the next term. Nested terms are counted instead of recursing
+    /// into them, so deeply nested input can't overflow the stack
+    pub(crate) fn skip_term(&mut self) -> Result<()> {
+        let mut pending: usize = 1;
+        while pending > 0 {
+            pending -= 1;
+            // Bytes of the term body and the number of terms nested into it
+            let (length, nested) = match self.read_u8()? {
+                70 => (8, 0),
+                77 => (1 + self.read_u32()? as usize, 0),
+                82 | 97 => (1, 0),
+                98 => (4, 0),
+                99 => (31, 0),
+                100 | 107 | 118 => (self.read_u16()? as usize, 0),
+                115 | 119 => (self.read_u8()? as usize, 0),
+                109 => (self.read_u32()? as usize, 0),
+                110 => (1 + self.read_u8()? as usize, 0),
+                111 => (1 + self.read_u32()? as usize, 0),
+                112 => {
+                    let size = self.read_u32()? as usize;
+                    (size.checked_sub(4).ok_or(Error::EndOfStream)?, 0)
+                }
+                88 | 120 => {
+                    self.skip_atom()?;
+                    (12, 0)
+                }
+                89 => {
+                    self.skip_atom()?;
+                    (8, 0)
+                }
+                101 | 102 => {
+                    self.skip_atom()?;
+                    (5, 0)
+                }
+                103 => {
+                    self.skip_atom()?;
+                    (9, 0)
+                }
+                90 => {
+                    let words = self.read_u16()? as usize;
+                    self.skip_atom()?;
+                    (4 + 4 * words, 0)
+                }
+                114 => {
+                    let words = self.read_u16()? as usize;
+                    self.skip_atom()?;
+                    (1 + 4 * words, 0)
+                }
+                104 => (0, self.read_u8()? as usize),
+                105 => (0, self.read_u32()? as usize),
+                106 => (0, 0),
+                // Elements and the tail
+                108 => (0, self.read_u32()? as usize + 1),
+                113 => (0, 3),
+                116 => (0, 2 * self.read_u32()? as usize),
+                _ => return Err(Error::InvalidTag),
+            };
+            self.skip(length)?;
+            pending += nested;
+            // Every term takes at least one byte
+            if pending > self.data.len() - self.position {
+                return Err(Error::EndOfStream);
+            }
+        }
+        Ok(())
+    }
+
+    /// Skip the node atom of a pid, a port or a reference
+    fn skip_atom(&mut self) -> Result<()> {
+        let length = match self.read_u8()? {
+            82 => 1,
+            100 | 118 => self.read_u16()? as usize,
+            115 | 119 => self.read_u8()? as usize,
+            _ => return Err(Error::InvalidTag),
+        };
+        self.skip(length)
+    }
+}
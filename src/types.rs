@@ -1,23 +1,39 @@
 // For more information about basic "External Term Format" types you can read
 // on the next page: http://erlang.org/doc/apps/erts/erl_ext_dist.html
+use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
 use num::bigint::BigInt;
 
+use crate::errors::{Error, Result};
+
 pub const BERT_LABEL: &str = "bert";
 pub const ETF_VERSION: u8 = 131u8;
+/// Maximum number of characters in an atom
+pub const MAX_ATOM_LENGTH: usize = 255;
 
 
 // The BERT encoding is identical to Erlang's external term format except that
 // it is restricted to the following data type identifiers: 97-100, 104-111.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BertTag {
+    DistHeader = 68,    // 68, DIST_HEADER
+    DistFragHeader = 69,// 69, DIST_FRAG_HEADER
     NewFloat = 70,      // 70, NEW_FLOAT_EXT
+    BitBinary = 77,     // 77, BIT_BINARY_EXT
+    Compressed = 80,    // 80, COMPRESSED (zlib compressed term)
+    AtomCacheRef = 82,  // 82, ATOM_CACHE_REF
+    NewPid = 88,        // 88, NEW_PID_EXT
+    NewPort = 89,       // 89, NEW_PORT_EXT
+    NewerReference = 90,// 90, NEWER_REFERENCE_EXT
 
     SmallInteger = 97,  // 97, SMALL_INTEGER_EXT
     Integer = 98,       // 98, INTEGER_EXT
     Float = 99,         // 99, FLOAT_EXT (deprecated; using for deserialize)
     Atom = 100,         // 100, ATOM_EXT
+    Reference = 101,    // 101, REFERENCE_EXT (deprecated; using for deserialize)
+    Port = 102,         // 102, PORT_EXT (deprecated; using for deserialize)
+    Pid = 103,          // 103, PID_EXT (deprecated; using for deserialize)
 
     SmallTuple = 104,   // 104, SMALL_TUPLE_EXT
     LargeTuple = 105,   // 105, LARGE_TUPLE_EXT
@@ -27,6 +43,61 @@ pub enum BertTag {
     Binary = 109,       // 109, BINARY_EXT
     SmallBigNum = 110,  // 110, SMALL_BIG_EXT
     LargeBigNum = 111,  // 111, LARGE_BIG_EXT
+
+    NewFun = 112,       // 112, NEW_FUN_EXT
+    Export = 113,       // 113, EXPORT_EXT
+    NewReference = 114, // 114, NEW_REFERENCE_EXT (using for deserialize)
+    SmallAtom = 115,    // 115, SMALL_ATOM_EXT
+
+    Map = 116,          // 116, MAP_EXT
+
+    AtomUtf8 = 118,     // 118, ATOM_UTF8_EXT
+    SmallAtomUtf8 = 119,// 119, SMALL_ATOM_UTF8_EXT
+    V4Port = 120,       // 120, V4_PORT_EXT
+}
+
+
+impl TryFrom<u8> for BertTag {
+    type Error = Error;
+
+    fn try_from(tag: u8) -> Result<BertTag> {
+        let tag = match tag {
+            68 => BertTag::DistHeader,
+            69 => BertTag::DistFragHeader,
+            70 => BertTag::NewFloat,
+            77 => BertTag::BitBinary,
+            80 => BertTag::Compressed,
+            82 => BertTag::AtomCacheRef,
+            88 => BertTag::NewPid,
+            89 => BertTag::NewPort,
+            90 => BertTag::NewerReference,
+            97 => BertTag::SmallInteger,
+            98 => BertTag::Integer,
+            99 => BertTag::Float,
+            100 => BertTag::Atom,
+            101 => BertTag::Reference,
+            102 => BertTag::Port,
+            103 => BertTag::Pid,
+            104 => BertTag::SmallTuple,
+            105 => BertTag::LargeTuple,
+            106 => BertTag::Nil,
+            107 => BertTag::String,
+            108 => BertTag::List,
+            109 => BertTag::Binary,
+            110 => BertTag::SmallBigNum,
+            111 => BertTag::LargeBigNum,
+            112 => BertTag::NewFun,
+            113 => BertTag::Export,
+            114 => BertTag::NewReference,
+            115 => BertTag::SmallAtom,
+            116 => BertTag::Map,
+            118 => BertTag::AtomUtf8,
+            119 => BertTag::SmallAtomUtf8,
+            120 => BertTag::V4Port,
+            _ => return Err(Error::InvalidTag),
+        };
+        Ok(tag)
+    }
 }
 
 
@@ -106,6 +177,200 @@ pub struct RegexStruct {
 }
 
 
+/// Erlang process identifier. Encoded as NEW_PID_EXT, which is the only
+/// format sent by OTP 23 and newer, unless it was decoded with other tags
+#[derive(Debug, Clone)]
+pub struct BertPid {
+    pub node: String,
+    pub id: u32,
+    pub serial: u32,
+    pub creation: u32,
+    /// Tags the pid was decoded with, so it is encoded back byte-exact.
+    /// Not taken into account when pids are compared
+    pub encoding: Option<PidEncoding>,
+}
+
+
+impl BertPid {
+    pub fn new(node: &str, id: u32, serial: u32, creation: u32) -> BertPid {
+        BertPid {
+            node: node.to_string(),
+            id,
+            serial,
+            creation,
+            encoding: None,
+        }
+    }
+}
+
+
+impl PartialEq for BertPid {
+    fn eq(&self, other: &BertPid) -> bool {
+        self.node == other.node
+            && self.id == other.id
+            && self.serial == other.serial
+            && self.creation == other.creation
+    }
+}
+
+
+impl Eq for BertPid {}
+
+
+impl Hash for BertPid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.node.hash(state);
+        self.id.hash(state);
+        self.serial.hash(state);
+        self.creation.hash(state);
+    }
+}
+
+
+/// Tags of a decoded pid: PID_EXT or NEW_PID_EXT, and the tag of its node
+/// atom. Node atoms which can't be written with their tag anymore, like
+/// ATOM_CACHE_REF or a changed node, are encoded as any other atom
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PidEncoding {
+    pub tag: BertTag,
+    pub node_tag: BertTag,
+}
+
+
+/// Erlang reference, made by `make_ref()`. Always encoded as
+/// NEWER_REFERENCE_EXT
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BertReference {
+    pub node: String,
+    pub creation: u32,
+    pub id: Vec<u32>,
+}
+
+
+impl BertReference {
+    pub fn new(node: &str, creation: u32, id: Vec<u32>) -> BertReference {
+        BertReference {
+            node: node.to_string(),
+            creation,
+            id,
+        }
+    }
+}
+
+
+/// Erlang port identifier. Encoded as NEW_PORT_EXT when the id fits into
+/// 32 bits, otherwise as V4_PORT_EXT
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BertPort {
+    pub node: String,
+    pub id: u64,
+    pub creation: u32,
+}
+
+
+impl BertPort {
+    pub fn new(node: &str, id: u64, creation: u32) -> BertPort {
+        BertPort {
+            node: node.to_string(),
+            id,
+            creation,
+        }
+    }
+}
+
+
+/// Binary which length in bits is not divisible by 8. Only the high
+/// `bits_in_last_byte` bits of the last byte belong to the value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BertBitString {
+    pub bytes: Vec<u8>,
+    pub bits_in_last_byte: u8,
+}
+
+
+impl BertBitString {
+    pub fn new(bytes: Vec<u8>, bits_in_last_byte: u8) -> BertBitString {
+        BertBitString {
+            bytes,
+            bits_in_last_byte,
+        }
+    }
+
+    /// Make the bitstring, failing with `InvalidBitCount` when the number
+    /// of bits is not accepted by Erlang
+    pub fn try_new(bytes: Vec<u8>, bits_in_last_byte: u8) -> Result<BertBitString> {
+        check_bits_in_last_byte(bytes.len(), bits_in_last_byte)?;
+        Ok(BertBitString::new(bytes, bits_in_last_byte))
+    }
+}
+
+
+/// Erlang accepts from 1 up to 8 bits in the last byte of a bitstring, and
+/// 0 bits only when there are no bytes
+pub(crate) fn check_bits_in_last_byte(length: usize, bits_in_last_byte: u8) -> Result<()> {
+    match (length, bits_in_last_byte) {
+        (0, 0) | (1.., 1..=8) => Ok(()),
+        _ => Err(Error::InvalidBitCount(bits_in_last_byte)),
+    }
+}
+
+
+/// External fun, written in Erlang as `fun Module:Function/Arity`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BertExportFun {
+    pub module: String,
+    pub function: String,
+    pub arity: u8,
+}
+
+
+impl BertExportFun {
+    pub fn new(module: &str, function: &str, arity: u8) -> BertExportFun {
+        BertExportFun {
+            module: module.to_string(),
+            function: function.to_string(),
+            arity,
+        }
+    }
+}
+
+
+/// Erlang closure (NEW_FUN_EXT), kept as-is to be sent back untouched.
+/// Fields which are terms on the wire (`module`, `old_index`, `old_uniq`,
+/// `pid` and free variables) are stored as raw encoded terms without the
+/// version byte, so the closure is re-encoded byte-for-byte
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BertClosure {
+    pub arity: u8,
+    pub uniq: [u8; 16],
+    pub index: u32,
+    pub module: Vec<u8>,
+    pub old_index: Vec<u8>,
+    pub old_uniq: Vec<u8>,
+    pub pid: Vec<u8>,
+    pub free_vars: Vec<Vec<u8>>,
+}
+
+
+/// List which tail is not necessarily nil, like `[a, b | c]` in Erlang.
+/// Without elements the list is encoded as its tail only
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImproperList<T, Tail> {
+    pub elements: Vec<T>,
+    pub tail: Tail,
+}
+
+
+impl<T, Tail> ImproperList<T, Tail> {
+    pub fn new(elements: Vec<T>, tail: Tail) -> ImproperList<T, Tail> {
+        ImproperList {
+            elements,
+            tail,
+        }
+    }
+}
+
+
 enum_str!(
     RegexOption {
         Extended("extended"),
@@ -1,6 +1,8 @@
 use byteorder::{BigEndian, WriteBytesExt};
+use num::bigint::{BigInt, Sign};
 
-use crate::types::{BERT_LABEL, BertTag};
+use crate::errors::{Error, Result};
+use crate::types::{BERT_LABEL, MAX_ATOM_LENGTH, BertTag};
 
 
 pub fn merge_terms(term_1: Vec<u8>, term_2: Vec<u8>) -> Vec<u8> {
@@ -10,21 +12,195 @@ pub fn merge_terms(term_1: Vec<u8>, term_2: Vec<u8>) -> Vec<u8> {
 }
 
 
+/// Encode the string as the STRING_EXT body: the length and the bytes.
+/// Panics when the string is longer than 65535 bytes, which STRING_EXT
+/// can't hold; `get_string` encodes strings of any length
 pub fn str_to_binary(data: &str) -> Vec<u8> {
     let binary_string = data.as_bytes();
-    let binary_length = binary_string.len() as i16;
+    let binary_length = u16::try_from(binary_string.len())
+        .expect("STRING_EXT can't hold more than 65535 bytes");
     let mut binary = vec![];
-    binary.write_i16::<BigEndian>(binary_length).unwrap();
+    binary.write_u16::<BigEndian>(binary_length).unwrap();
     binary.extend(binary_string.iter());
     binary
 }
 
 
+/// Encode the string as STRING_EXT. Strings longer than 65535 bytes don't
+/// fit into it, so they are encoded as a list of bytes, like Erlang does
+pub fn get_string(data: &str) -> Vec<u8> {
+    let binary_string = data.as_bytes();
+    if binary_string.len() <= u16::MAX as usize {
+        return merge_terms(vec![BertTag::String as u8], str_to_binary(data));
+    }
+
+    let mut binary = vec![BertTag::List as u8];
+    binary.write_u32::<BigEndian>(binary_string.len() as u32).unwrap();
+    for byte in binary_string {
+        binary.extend([BertTag::SmallInteger as u8, *byte]);
+    }
+    binary.extend(get_nil());
+    binary
+}
+
+
+/// Encode the string as Latin-1 bytes. Returns `None` when the string
+/// contains characters outside of the Latin-1 range
+pub fn to_latin1(data: &str) -> Option<Vec<u8>> {
+    data.chars()
+        .map(|character| u8::try_from(u32::from(character)).ok())
+        .collect()
+}
+
+
+/// Encode the lowercased name as an atom. Panics when the name is longer
+/// than 255 characters, `try_get_atom` returns an error instead
 pub fn get_atom(name: &str) -> Vec<u8> {
-    let header = vec![BertTag::Atom as u8];
-    let normalized_name = &name.to_string().to_lowercase();
-    let name = str_to_binary(normalized_name);
-    merge_terms(header, name)
+    try_get_atom(name).unwrap()
+}
+
+
+/// Encode the lowercased name as an atom. Fails with `AtomTooLong` for
+/// names longer than 255 characters
+pub fn try_get_atom(name: &str) -> Result<Vec<u8>> {
+    get_exact_atom(&name.to_lowercase())
+}
+
+
+/// Encode the name as an atom without any normalization. Names with
+/// characters outside of the Latin-1 range are encoded as ATOM_UTF8_EXT,
+/// because ATOM_EXT can't represent them
+pub fn get_exact_atom(name: &str) -> Result<Vec<u8>> {
+    check_atom_length(name)?;
+    let (tag, text) = match to_latin1(name) {
+        Some(latin1) => (BertTag::Atom, latin1),
+        None => (BertTag::AtomUtf8, name.as_bytes().to_vec()),
+    };
+
+    let mut binary = vec![tag as u8];
+    binary.write_u16::<BigEndian>(text.len() as u16).unwrap();
+    binary.extend(text.iter());
+    Ok(binary)
+}
+
+
+/// Encode the name as ATOM_UTF8_EXT, which is the default atom encoding
+/// since OTP 26 (`{minor_version, 2}`)
+pub fn get_utf8_atom(name: &str) -> Result<Vec<u8>> {
+    check_atom_length(name)?;
+    let text = name.as_bytes();
+    let mut binary = vec![BertTag::AtomUtf8 as u8];
+    binary.write_u16::<BigEndian>(text.len() as u16).unwrap();
+    binary.extend(text.iter());
+    Ok(binary)
+}
+
+
+/// Encode the name as SMALL_ATOM_EXT, or as SMALL_ATOM_UTF8_EXT if the name
+/// is not Latin-1. Falls back to `get_exact_atom` for names longer than
+/// 255 bytes
+pub fn get_small_atom(name: &str) -> Result<Vec<u8>> {
+    check_atom_length(name)?;
+    let (tag, text) = match to_latin1(name) {
+        Some(latin1) => (BertTag::SmallAtom, latin1),
+        None => (BertTag::SmallAtomUtf8, name.as_bytes().to_vec()),
+    };
+    if text.len() > u8::MAX as usize {
+        return get_exact_atom(name);
+    }
+
+    let mut binary = vec![tag as u8, text.len() as u8];
+    binary.extend(text.iter());
+    Ok(binary)
+}
+
+
+/// Encode the name as SMALL_ATOM_UTF8_EXT. Falls back to `get_utf8_atom`
+/// for names longer than 255 bytes
+pub fn get_small_utf8_atom(name: &str) -> Result<Vec<u8>> {
+    check_atom_length(name)?;
+    let text = name.as_bytes();
+    if text.len() > u8::MAX as usize {
+        return get_utf8_atom(name);
+    }
+
+    let mut binary = vec![BertTag::SmallAtomUtf8 as u8, text.len() as u8];
+    binary.extend(text.iter());
+    Ok(binary)
+}
+
+
+/// Erlang doesn't accept atoms longer than 255 characters. The limit also
+/// keeps the length of the encoded name within 16 bits
+fn check_atom_length(name: &str) -> Result<()> {
+    if name.chars().count() > MAX_ATOM_LENGTH {
+        return Err(Error::AtomTooLong(name.to_string()));
+    }
+    Ok(())
+}
+
+
+/// Encode the float as FLOAT_EXT: the value is written in the "%.20e"
+/// format and padded with zeros up to 31 bytes. Erlang can't read NaN and
+/// infinities, so they are rejected
+pub fn get_legacy_float(value: f64) -> Result<Vec<u8>> {
+    if !value.is_finite() {
+        return Err(Error::NonFiniteFloat(value));
+    }
+
+    let formatted = format!("{:.20e}", value);
+    let text = match formatted.split_once('e') {
+        Some((mantissa, exponent)) => {
+            let exponent: i32 = exponent.parse().unwrap();
+            format!("{}e{:+03}", mantissa, exponent)
+        }
+        None => formatted,
+    };
+
+    let mut binary = vec![BertTag::Float as u8];
+    binary.extend(text.bytes().take(31));
+    binary.resize(32, 0);
+    Ok(binary)
+}
+
+
+/// Encode the integer with the smallest tag that can hold it, the same way
+/// as `erlang:term_to_binary` does
+pub fn get_integer(value: i128) -> Vec<u8> {
+    if let Ok(small_integer) = u8::try_from(value) {
+        vec![BertTag::SmallInteger as u8, small_integer]
+    } else if let Ok(integer) = i32::try_from(value) {
+        let mut binary = vec![BertTag::Integer as u8];
+        binary.write_i32::<BigEndian>(integer).unwrap();
+        binary
+    } else {
+        get_bignum(&BigInt::from(value))
+    }
+}
+
+
+/// Encode the integer as SMALL_BIG_EXT, or as LARGE_BIG_EXT when its
+/// magnitude takes more than 255 bytes
+pub fn get_bignum(value: &BigInt) -> Vec<u8> {
+    let (num_sign, bytes) = value.to_bytes_le();
+    let length = bytes.len();
+    let sign: u8 = match num_sign {
+        Sign::Plus | Sign::NoSign => 0,
+        Sign::Minus => 1,
+    };
+    let mut binary: Vec<u8> = match length {
+        0..=255 => {
+            vec![BertTag::SmallBigNum as u8, length as u8, sign]
+        },
+        _ => {
+            let mut binary = vec![BertTag::LargeBigNum as u8];
+            binary.write_u32::<BigEndian>(length as u32).unwrap();
+            binary.write_u8(sign).unwrap();
+            binary
+        }
+    };
+    binary.extend(bytes.iter());
+    binary
 }
 
 
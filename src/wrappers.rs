@@ -1,42 +1,44 @@
 // Wrappers for types which are not supported by serde-rs and
 // described by BERT specification.
 use std::fmt;
+use std::marker::PhantomData;
 
-use byteorder::{BigEndian, WriteBytesExt};
 use num::bigint::{BigInt, Sign};
 use serde::{ser, de};
 
-use crate::types::{BertTag, BertBigInteger, BertTime, BertRegex, RegexOption};
+use crate::term::{Term, decode_term};
+use crate::types::{
+    MAX_ATOM_LENGTH, BertTag, BertBigInteger, BertTime, BertRegex, BertPid,
+    BertReference, BertPort, BertBitString, BertExportFun, BertClosure,
+    ImproperList, PidEncoding, RegexOption, check_bits_in_last_byte
+};
+use crate::utils::{get_bignum, to_latin1};
 
 
 pub const BIGNUM_STRUCT_NAME: &str = "_BertBigNumber";
 pub const TIME_STRUCT_NAME: &str = "_BertTimeStruct";
 pub const REGEX_STRUCT_NAME: &str = "_BertRegexStruct";
 pub const REGEX_OPTION_ENUM_NAME: &str = "_BertRegexOptionsEnum";
+pub const PID_STRUCT_NAME: &str = "_BertPid";
+pub const LEGACY_PID_STRUCT_NAME: &str = "_BertLegacyPid";
+pub const REFERENCE_STRUCT_NAME: &str = "_BertReference";
+pub const PORT_STRUCT_NAME: &str = "_BertPort";
+pub const V4_PORT_STRUCT_NAME: &str = "_BertV4Port";
+pub const BITSTRING_STRUCT_NAME: &str = "_BertBitString";
+pub const EXPORT_FUN_STRUCT_NAME: &str = "_BertExportFun";
+pub const CLOSURE_STRUCT_NAME: &str = "_BertClosure";
+pub const IMPROPER_LIST_STRUCT_NAME: &str = "_BertImproperList";
+pub const TERM_FIELD_STRUCT_NAME: &str = "_BertTermField";
+pub const TERM_STRUCT_NAME: &str = "_BertTerm";
+
+/// Maximum number of id words in a reference
+const MAX_REFERENCE_WORDS: usize = 5;
 
 impl ser::Serialize for BertBigInteger {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where S: ser::Serializer
     {
-        let (num_sign, bytes) = self.to_bytes_le();
-        let length = bytes.len();
-        let sign: u8 = match num_sign {
-            Sign::Plus | Sign::NoSign => 0,
-            Sign::Minus => 1,
-        };
-        let mut binary: Vec<u8> = match length {
-            0..=255 => {
-                vec![BertTag::SmallBigNum as u8, length as u8, sign]
-            },
-            _ => {
-                let mut binary = vec![BertTag::LargeBigNum as u8];
-                binary.write_u32::<BigEndian>(length as u32).unwrap();
-                binary.write_u8(sign).unwrap();
-                binary
-            }
-        };
-        binary.extend(bytes.iter());
-
+        let binary = get_bignum(self);
         serializer.serialize_newtype_struct(BIGNUM_STRUCT_NAME, serde_bytes::Bytes::new(&binary))
     }
 }
@@ -79,6 +81,213 @@ impl ser::Serialize for BertRegex {
     }
 }
 
+impl ser::Serialize for BertPid {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // Fields are written as-is in the NEW_PID_EXT layout, or in the
+        // PID_EXT one with an 8-bit creation when the pid was decoded from
+        // it. The node atom keeps the tag it was decoded with
+        use serde::ser::SerializeStruct;
+        let legacy = self.encoding.is_some_and(|encoding| encoding.tag == BertTag::Pid)
+            && self.creation <= u8::MAX as u32;
+        let name = if legacy { LEGACY_PID_STRUCT_NAME } else { PID_STRUCT_NAME };
+        let mut state = serializer.serialize_struct(name, 4)?;
+        match self.encoding.and_then(|encoding| encode_node(&self.node, encoding.node_tag)) {
+            Some(node) => state.serialize_field("node", serde_bytes::Bytes::new(&node))?,
+            None => state.serialize_field("node", &self.node)?,
+        }
+        state.serialize_field("id", &self.id)?;
+        state.serialize_field("serial", &self.serial)?;
+        if legacy {
+            state.serialize_field("creation", &(self.creation as u8))?;
+        } else {
+            state.serialize_field("creation", &self.creation)?;
+        }
+        state.end()
+    }
+}
+
+
+/// Encode the node atom with the passed tag. Returns `None` when the tag
+/// is not an atom tag or can't represent the node
+fn encode_node(node: &str, tag: BertTag) -> Option<Vec<u8>> {
+    if node.chars().count() > MAX_ATOM_LENGTH {
+        return None;
+    }
+
+    let text = match tag {
+        BertTag::Atom | BertTag::SmallAtom => to_latin1(node)?,
+        BertTag::AtomUtf8 | BertTag::SmallAtomUtf8 => node.as_bytes().to_vec(),
+        _ => return None,
+    };
+    let mut binary = vec![tag as u8];
+    match tag {
+        BertTag::SmallAtom | BertTag::SmallAtomUtf8 => {
+            binary.push(u8::try_from(text.len()).ok()?);
+        }
+        _ => binary.extend((text.len() as u16).to_be_bytes()),
+    }
+    binary.extend(text);
+    Some(binary)
+}
+
+impl ser::Serialize for BertReference {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // Fields are written as-is in the NEWER_REFERENCE_EXT layout, which
+        // starts with the number of id words. Erlang accepts from 1 up to 5
+        use serde::ser::SerializeStruct;
+        if !(1..=MAX_REFERENCE_WORDS).contains(&self.id.len()) {
+            return Err(ser::Error::custom(format!(
+                "reference id must have 1 to {} words, got {}",
+                MAX_REFERENCE_WORDS, self.id.len()
+            )));
+        }
+        let mut state = serializer.serialize_struct(REFERENCE_STRUCT_NAME, 4)?;
+        state.serialize_field("len", &(self.id.len() as u16))?;
+        state.serialize_field("node", &self.node)?;
+        state.serialize_field("creation", &self.creation)?;
+        state.serialize_field("id", &self.id)?;
+        state.end()
+    }
+}
+
+impl ser::Serialize for BertPort {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // Fields are written as-is in the NEW_PORT_EXT layout, or in the
+        // V4_PORT_EXT one with a 64-bit id when it doesn't fit into 32 bits
+        use serde::ser::SerializeStruct;
+        match u32::try_from(self.id) {
+            Ok(id) => {
+                let mut state = serializer.serialize_struct(PORT_STRUCT_NAME, 3)?;
+                state.serialize_field("node", &self.node)?;
+                state.serialize_field("id", &id)?;
+                state.serialize_field("creation", &self.creation)?;
+                state.end()
+            }
+            Err(_) => {
+                let mut state = serializer.serialize_struct(V4_PORT_STRUCT_NAME, 3)?;
+                state.serialize_field("node", &self.node)?;
+                state.serialize_field("id", &self.id)?;
+                state.serialize_field("creation", &self.creation)?;
+                state.end()
+            }
+        }
+    }
+}
+
+impl ser::Serialize for BertBitString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // Fields are written as-is in the BIT_BINARY_EXT layout, with the
+        // unused bits of the last byte set to zero like Erlang does
+        use serde::ser::SerializeStruct;
+        check_bits_in_last_byte(self.bytes.len(), self.bits_in_last_byte)
+            .map_err(ser::Error::custom)?;
+        let mut bytes = self.bytes.clone();
+        if let Some(last) = bytes.last_mut() {
+            *last &= 0xFF << (8 - self.bits_in_last_byte);
+        }
+        let mut state = serializer.serialize_struct(BITSTRING_STRUCT_NAME, 3)?;
+        state.serialize_field("len", &(bytes.len() as u32))?;
+        state.serialize_field("bits_in_last_byte", &self.bits_in_last_byte)?;
+        state.serialize_field("bytes", serde_bytes::Bytes::new(&bytes))?;
+        state.end()
+    }
+}
+
+/// Wrapper to write a field of the Erlang specific type as a regular term
+/// instead of its raw wire layout
+struct TermField<'a, T>(&'a T);
+
+impl<'a, T: ser::Serialize> ser::Serialize for TermField<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        serializer.serialize_newtype_struct(TERM_FIELD_STRUCT_NAME, self.0)
+    }
+}
+
+impl ser::Serialize for BertExportFun {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // Fields are written in the EXPORT_EXT layout, where the arity
+        // is a SMALL_INTEGER_EXT term
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct(EXPORT_FUN_STRUCT_NAME, 3)?;
+        state.serialize_field("module", &self.module)?;
+        state.serialize_field("function", &self.function)?;
+        state.serialize_field("arity", &TermField(&self.arity))?;
+        state.end()
+    }
+}
+
+impl ser::Serialize for BertClosure {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // Fields are written as-is in the NEW_FUN_EXT layout, which starts
+        // with the total size of the closure (including the size field)
+        use serde::ser::SerializeStruct;
+        let free_vars: Vec<&serde_bytes::Bytes> = self.free_vars
+            .iter()
+            .map(|term| serde_bytes::Bytes::new(term))
+            .collect();
+        let size = 4 + 1 + 16 + 4 + 4
+            + self.module.len() + self.old_index.len()
+            + self.old_uniq.len() + self.pid.len()
+            + self.free_vars.iter().map(|term| term.len()).sum::<usize>();
+
+        let mut state = serializer.serialize_struct(CLOSURE_STRUCT_NAME, 10)?;
+        state.serialize_field("size", &(size as u32))?;
+        state.serialize_field("arity", &self.arity)?;
+        state.serialize_field("uniq", serde_bytes::Bytes::new(&self.uniq))?;
+        state.serialize_field("index", &self.index)?;
+        state.serialize_field("num_free", &(self.free_vars.len() as u32))?;
+        state.serialize_field("module", serde_bytes::Bytes::new(&self.module))?;
+        state.serialize_field("old_index", serde_bytes::Bytes::new(&self.old_index))?;
+        state.serialize_field("old_uniq", serde_bytes::Bytes::new(&self.old_uniq))?;
+        state.serialize_field("pid", serde_bytes::Bytes::new(&self.pid))?;
+        state.serialize_field("free_vars", &free_vars)?;
+        state.end()
+    }
+}
+
+impl<T: ser::Serialize, Tail: ser::Serialize> ser::Serialize for ImproperList<T, Tail> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // The serializer writes the list header for all fields except
+        // the last one, which becomes the list tail
+        use serde::ser::SerializeTupleStruct;
+        let length = self.elements.len() + 1;
+        let mut state = serializer.serialize_tuple_struct(IMPROPER_LIST_STRUCT_NAME, length)?;
+        for element in &self.elements {
+            state.serialize_field(element)?;
+        }
+        state.serialize_field(&self.tail)?;
+        state.end()
+    }
+}
+
+impl ser::Serialize for Term {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        // The term is written as-is, like bignums
+        let mut binary = vec![];
+        self.write(&mut binary).map_err(ser::Error::custom)?;
+        serializer.serialize_newtype_struct(TERM_STRUCT_NAME, serde_bytes::Bytes::new(&binary))
+    }
+}
+
+
 impl<'de> de::Deserialize<'de> for BertBigInteger {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where D: de::Deserializer<'de>
@@ -177,3 +386,319 @@ impl<'de> de::Deserialize<'de> for BertRegex {
         )
     }
 }
+
+
+impl<'de> de::Deserialize<'de> for BertPid {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents pid fields as a seq of
+        // [node, id, serial, creation, tag, node_tag] for both PID_EXT and
+        // NEW_PID_EXT. The tags are optional
+        struct PidVisitor;
+
+        impl<'de> de::Visitor<'de> for PidVisitor {
+            type Value = BertPid;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang pid with node, id, serial and creation")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BertPid, A::Error> {
+                let node: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let id: u32 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let serial: u32 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let creation: u32 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                let tag: Option<u8> = seq.next_element()?;
+                let node_tag: Option<u8> = seq.next_element()?;
+
+                let mut pid = BertPid::new(&node, id, serial, creation);
+                if let (Some(tag), Some(node_tag)) = (tag, node_tag) {
+                    let tag = BertTag::try_from(tag).map_err(de::Error::custom)?;
+                    let node_tag = BertTag::try_from(node_tag).map_err(de::Error::custom)?;
+                    pid.encoding = Some(PidEncoding { tag, node_tag });
+                }
+                Ok(pid)
+            }
+        }
+
+        deserializer.deserialize_struct(
+            PID_STRUCT_NAME,
+            &["node", "id", "serial", "creation"],
+            PidVisitor,
+        )
+    }
+}
+
+
+impl<'de> de::Deserialize<'de> for BertReference {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents reference fields as a seq of
+        // [node, creation, id_words] for all reference encodings
+        struct ReferenceVisitor;
+
+        impl<'de> de::Visitor<'de> for ReferenceVisitor {
+            type Value = BertReference;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang reference with node, creation and id")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BertReference, A::Error> {
+                let node: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let creation: u32 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let id: Vec<u32> = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                Ok(BertReference::new(&node, creation, id))
+            }
+        }
+
+        deserializer.deserialize_struct(
+            REFERENCE_STRUCT_NAME,
+            &["node", "creation", "id"],
+            ReferenceVisitor,
+        )
+    }
+}
+
+
+impl<'de> de::Deserialize<'de> for BertPort {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents port fields as a seq of
+        // [node, id, creation] for all port encodings
+        struct PortVisitor;
+
+        impl<'de> de::Visitor<'de> for PortVisitor {
+            type Value = BertPort;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang port with node, id and creation")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BertPort, A::Error> {
+                let node: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let id: u64 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let creation: u32 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                Ok(BertPort::new(&node, id, creation))
+            }
+        }
+
+        deserializer.deserialize_struct(
+            PORT_STRUCT_NAME,
+            &["node", "id", "creation"],
+            PortVisitor,
+        )
+    }
+}
+
+
+impl<'de> de::Deserialize<'de> for BertBitString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents bitstring fields as a seq of
+        // [bytes, bits_in_last_byte]
+        struct BitStringVisitor;
+
+        impl<'de> de::Visitor<'de> for BitStringVisitor {
+            type Value = BertBitString;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang bitstring with bytes and bits in the last byte")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BertBitString, A::Error> {
+                let bytes: serde_bytes::ByteBuf = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let bits_in_last_byte: u8 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                Ok(BertBitString::new(bytes.into_vec(), bits_in_last_byte))
+            }
+        }
+
+        deserializer.deserialize_struct(
+            BITSTRING_STRUCT_NAME,
+            &["bytes", "bits_in_last_byte"],
+            BitStringVisitor,
+        )
+    }
+}
+
+
+impl<'de> de::Deserialize<'de> for BertExportFun {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents export fields as a seq of
+        // [module, function, arity]
+        struct ExportFunVisitor;
+
+        impl<'de> de::Visitor<'de> for ExportFunVisitor {
+            type Value = BertExportFun;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang external fun with module, function and arity")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BertExportFun, A::Error> {
+                let module: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let function: String = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let arity: u8 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                Ok(BertExportFun::new(&module, &function, arity))
+            }
+        }
+
+        deserializer.deserialize_struct(
+            EXPORT_FUN_STRUCT_NAME,
+            &["module", "function", "arity"],
+            ExportFunVisitor,
+        )
+    }
+}
+
+
+impl<'de> de::Deserialize<'de> for BertClosure {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents closure fields as a seq of
+        // [arity, uniq, index, module, old_index, old_uniq, pid, free_vars],
+        // where the terms are raw encoded bytes
+        struct ClosureVisitor;
+
+        impl<'de> de::Visitor<'de> for ClosureVisitor {
+            type Value = BertClosure;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang closure")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BertClosure, A::Error> {
+                let arity: u8 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let uniq: serde_bytes::ByteBuf = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let uniq: [u8; 16] = uniq.as_slice().try_into()
+                    .map_err(|_| de::Error::invalid_length(uniq.len(), &"16 bytes of uniq"))?;
+                let index: u32 = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                let module: serde_bytes::ByteBuf = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+                let old_index: serde_bytes::ByteBuf = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(4, &self))?;
+                let old_uniq: serde_bytes::ByteBuf = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(5, &self))?;
+                let pid: serde_bytes::ByteBuf = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(6, &self))?;
+                let free_vars: Vec<serde_bytes::ByteBuf> = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(7, &self))?;
+                Ok(BertClosure {
+                    arity,
+                    uniq,
+                    index,
+                    module: module.into_vec(),
+                    old_index: old_index.into_vec(),
+                    old_uniq: old_uniq.into_vec(),
+                    pid: pid.into_vec(),
+                    free_vars: free_vars.into_iter().map(|term| term.into_vec()).collect(),
+                })
+            }
+        }
+
+        deserializer.deserialize_struct(
+            CLOSURE_STRUCT_NAME,
+            &["arity", "uniq", "index", "module", "old_index", "old_uniq", "pid", "free_vars"],
+            ClosureVisitor,
+        )
+    }
+}
+
+
+impl<'de, T, Tail> de::Deserialize<'de> for ImproperList<T, Tail>
+    where T: de::Deserialize<'de>, Tail: de::Deserialize<'de>
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents the list as a seq of the elements
+        // followed by the tail, with the exact length as a size hint
+        struct ImproperListVisitor<T, Tail>(PhantomData<(T, Tail)>);
+
+        impl<'de, T, Tail> de::Visitor<'de> for ImproperListVisitor<T, Tail>
+            where T: de::Deserialize<'de>, Tail: de::Deserialize<'de>
+        {
+            type Value = ImproperList<T, Tail>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an Erlang list with elements and a tail")
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let length = match seq.size_hint() {
+                    Some(length) if length > 0 => length,
+                    _ => return Err(de::Error::custom("length of the list is unknown")),
+                };
+                let mut elements = Vec::with_capacity((length - 1).min(4096));
+                for index in 0..length - 1 {
+                    let element: T = seq.next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(index, &self))?;
+                    elements.push(element);
+                }
+                let tail: Tail = seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(length - 1, &self))?;
+                Ok(ImproperList::new(elements, tail))
+            }
+        }
+
+        deserializer.deserialize_tuple_struct(
+            IMPROPER_LIST_STRUCT_NAME,
+            0,
+            ImproperListVisitor(PhantomData),
+        )
+    }
+}
+
+
+impl<'de> de::Deserialize<'de> for Term {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: de::Deserializer<'de>
+    {
+        // The deserializer presents the term as its encoded bytes, without
+        // the version number
+        struct TermVisitor;
+
+        impl<'de> de::Visitor<'de> for TermVisitor {
+            type Value = Term;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an encoded Erlang term")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Term, E> {
+                decode_term(v).map_err(E::custom)
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Term, E> {
+                self.visit_bytes(&v)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(TERM_STRUCT_NAME, TermVisitor)
+    }
+}
@@ -1,8 +1,13 @@
-use std::collections::BTreeMap;
+#![allow(clippy::approx_constant, clippy::bool_assert_comparison)]
+
+use std::collections::{BTreeMap, HashMap};
 
 use bert::{
-    ETF_VERSION, binary_to_term, term_to_binary,
-    BertBigInteger, BertTime, BertRegex, RegexOption,
+    ETF_VERSION, Error, Term, binary_to_term, binary_to_term_with_options,
+    term_to_binary, term_to_binary_with_options, term_to_binary_compressed,
+    DeserializerOptions, SerializerOptions, MapEncoding, MinorVersion,
+    BertBigInteger, BertTime, BertRegex, BertPid, BertReference, BertPort, BertBitString, BertExportFun, BertClosure,
+    ImproperList, RegexOption,
 };
 use num::bigint::BigInt;
 use serde::Deserialize;
@@ -21,6 +26,79 @@ fn test_deserialize_i32() {
 }
 
 
+#[test]
+fn test_deserialize_i64() {
+    let data = vec![ETF_VERSION, 97, 200];
+    assert_eq!(200i64, binary_to_term(&data).unwrap());
+
+    let data = vec![ETF_VERSION, 98, 255, 255, 255, 255];
+    assert_eq!(-1i64, binary_to_term(&data).unwrap());
+
+    let data = vec![ETF_VERSION, 110, 4, 1, 1, 0, 0, 128];
+    assert_eq!(-2147483649i64, binary_to_term(&data).unwrap());
+
+    let data = vec![ETF_VERSION, 110, 8, 1, 0, 0, 0, 0, 0, 0, 0, 128];
+    assert_eq!(i64::MIN, binary_to_term(&data).unwrap());
+}
+
+
+#[test]
+fn test_deserialize_u64() {
+    let data = vec![ETF_VERSION, 98, 0, 0, 3, 232];
+    assert_eq!(1000u64, binary_to_term(&data).unwrap());
+
+    let data = vec![ETF_VERSION, 110, 8, 0, 255, 255, 255, 255, 255, 255, 255, 255];
+    assert_eq!(u64::MAX, binary_to_term(&data).unwrap());
+
+    let data = vec![
+        ETF_VERSION,
+        111,                            // LargeBigNum
+        0, 0, 0, 4,                     // length 4
+        0,                              // sign: positive
+        0, 0, 0, 128                    // 2147483648
+    ];
+    assert_eq!(2147483648u64, binary_to_term(&data).unwrap());
+}
+
+
+#[test]
+fn test_deserialize_128bit_from_bignum() {
+    let data = vec![
+        ETF_VERSION, 110, 16, 0,
+        255, 255, 255, 255, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255
+    ];
+    assert_eq!(u128::MAX, binary_to_term(&data).unwrap());
+
+    let data = vec![
+        ETF_VERSION, 110, 16, 1,
+        0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 128
+    ];
+    assert_eq!(i128::MIN, binary_to_term(&data).unwrap());
+}
+
+
+#[test]
+fn test_deserialize_integer_overflow() {
+    // 2^64 doesn't fit into u64
+    let data = vec![ETF_VERSION, 110, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+    let result = binary_to_term::<u64>(&data);
+    assert_eq!(
+        "integer value is out of range for u64",
+        result.unwrap_err().to_string()
+    );
+
+    // -1 doesn't fit into any unsigned type
+    let data = vec![ETF_VERSION, 98, 255, 255, 255, 255];
+    assert!(binary_to_term::<u32>(&data).is_err());
+
+    // 1000 doesn't fit into u8
+    let data = vec![ETF_VERSION, 98, 0, 0, 3, 232];
+    assert!(binary_to_term::<u8>(&data).is_err());
+}
+
+
 #[test]
 fn test_deserialize_new_f64() {
     let data = vec![ETF_VERSION, 70, 64, 9, 30, 184, 81, 235, 133, 31];
@@ -39,6 +117,15 @@ fn test_deserialize_old_f64() {
 }
 
 
+#[test]
+fn test_deserialize_old_f64_full_precision() {
+    let mut data = vec![ETF_VERSION, 99];
+    data.extend(b"3.14159265358979311600e+00");
+    data.extend(vec![0; 5]);
+    assert_eq!(std::f64::consts::PI, binary_to_term(&data).unwrap());
+}
+
+
 #[test]
 fn test_deserialize_atom() {
     let data = vec![ETF_VERSION, 100, 0, 3, 110, 105, 108];
@@ -47,6 +134,38 @@ fn test_deserialize_atom() {
 }
 
 
+#[test]
+fn test_deserialize_latin1_atom() {
+    let data = vec![ETF_VERSION, 100, 0, 4, 99, 97, 102, 233];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("café", result);
+}
+
+
+#[test]
+fn test_deserialize_utf8_atom() {
+    let data = vec![ETF_VERSION, 118, 0, 8, 208, 186, 208, 187, 209, 142, 209, 135];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("ключ", result);
+}
+
+
+#[test]
+fn test_deserialize_small_atom() {
+    let data = vec![ETF_VERSION, 115, 4, 99, 97, 102, 233];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("café", result);
+}
+
+
+#[test]
+fn test_deserialize_small_utf8_atom() {
+    let data = vec![ETF_VERSION, 119, 3, 110, 105, 108];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("nil", result);
+}
+
+
 #[test]
 fn test_deserialize_string() {
     let data = vec![ETF_VERSION, 107, 0, 4, 98, 101, 114, 116];
@@ -55,6 +174,73 @@ fn test_deserialize_string() {
 }
 
 
+#[test]
+fn test_deserialize_latin1_string() {
+    // "héllo" in Erlang
+    let data = vec![ETF_VERSION, 107, 0, 5, 104, 233, 108, 108, 111];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("héllo", result);
+}
+
+
+#[test]
+fn test_deserialize_utf8_like_latin1_string() {
+    // "Ã©" in Erlang reads as UTF-8 "é", the codes are kept by Vec<u8>
+    let data = vec![ETF_VERSION, 107, 0, 2, 195, 169];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("é", result);
+    let result: Vec<u8> = binary_to_term(&data).unwrap();
+    assert_eq!(vec![195, 169], result);
+}
+
+
+#[test]
+fn test_deserialize_string_as_bytes() {
+    let data = vec![ETF_VERSION, 107, 0, 3, 1, 2, 255];
+    let result: Vec<u8> = binary_to_term(&data).unwrap();
+    assert_eq!(vec![1, 2, 255], result);
+    let result: Vec<i32> = binary_to_term(&data).unwrap();
+    assert_eq!(vec![1, 2, 255], result);
+}
+
+
+#[test]
+fn test_deserialize_charlist() {
+    // "ok ✓" in Erlang
+    let data = vec![
+        ETF_VERSION,
+        108, 0, 0, 0, 4,                    // List, length 4
+        97, 111,                            // 'o'
+        97, 107,                            // 'k'
+        97, 32,                             // ' '
+        98, 0, 0, 39, 19,                   // '✓'
+        106                                 // Nil
+    ];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("ok ✓", result);
+}
+
+
+#[test]
+fn test_deserialize_empty_list_as_string() {
+    let data = vec![ETF_VERSION, 106];
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!("", result);
+}
+
+
+#[test]
+fn test_deserialize_charlist_with_invalid_code() {
+    let data = vec![
+        ETF_VERSION,
+        108, 0, 0, 0, 1,                    // List, length 1
+        98, 0, 0, 216, 0,                   // surrogate code point
+        106                                 // Nil
+    ];
+    assert!(binary_to_term::<String>(&data).is_err());
+}
+
+
 #[test]
 fn test_deserialize_binary() {
     let data = vec![
@@ -89,6 +275,17 @@ fn test_deserialize_bool_false() {
     assert_eq!(false, binary_to_term(&data).unwrap());
 }
 
+#[test]
+fn test_deserialize_bool_utf8_atoms() {
+    let data = vec![
+        ETF_VERSION,
+        104, 2,                              // SmallTuple, arity 2
+        119, 4,  98, 101, 114, 116,          // small utf8 atom "bert"
+        119, 4, 116, 114, 117, 101           // small utf8 atom "true"
+    ];
+    assert_eq!(true, binary_to_term(&data).unwrap());
+}
+
 #[test]
 fn test_deserialize_list_i32() {
     let data = vec![
@@ -104,6 +301,13 @@ fn test_deserialize_list_i32() {
     assert_eq!(vec![1, 2, 3], result);
 }
 
+#[test]
+fn test_deserialize_nil_as_empty_list() {
+    let data = vec![ETF_VERSION, 106];
+    let result: Vec<i32> = binary_to_term(&data).unwrap();
+    assert!(result.is_empty());
+}
+
 #[test]
 fn test_deserialize_list_string() {
     let data = vec![
@@ -204,6 +408,111 @@ fn test_deserialize_struct() {
     assert_eq!(Color { r: 128, g: 64, b: 32 }, result);
 }
 
+#[test]
+fn test_deserialize_struct_utf8_atoms() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Color {
+        r: u8,
+        g: u8,
+    }
+
+    let data = vec![
+        ETF_VERSION,
+        104, 3,                              // SmallTuple, arity 3
+        119, 5, 99, 111, 108, 111, 114,      // small utf8 atom "color"
+
+        104, 2,                              // SmallTuple(2)
+        119, 1, 114,                         // small utf8 atom "r"
+        97, 128,                             // 128
+
+        104, 2,                              // SmallTuple(2)
+        118, 0, 1, 103,                      // utf8 atom "g"
+        97, 64,                              // 64
+    ];
+    let result: Color = binary_to_term(&data).unwrap();
+    assert_eq!(Color { r: 128, g: 64 }, result);
+}
+
+#[test]
+fn test_deserialize_bert_dict_utf8_atoms() {
+    let data = vec![
+        ETF_VERSION,
+        104, 3,                              // SmallTuple, arity 3
+        119, 4, 98, 101, 114, 116,           // small utf8 atom "bert"
+        119, 4, 100, 105, 99, 116,           // small utf8 atom "dict"
+
+        108,                                 // List
+        0, 0, 0, 1,                          // length 1
+
+        104, 2,                              // SmallTuple, arity 2
+        119, 2, 105, 100,                    // small utf8 atom "id"
+        98, 0, 0, 0, 4,                      // 4
+
+        106                                  // trailing Nil
+    ];
+    let result: BTreeMap<String, i32> = binary_to_term(&data).unwrap();
+    let mut expected = BTreeMap::new();
+    expected.insert("id".to_string(), 4);
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn test_deserialize_native_map() {
+    let data = vec![
+        ETF_VERSION,
+        116,                                 // Map
+        0, 0, 0, 2,                          // arity 2
+
+        109, 0, 0, 0, 4,                     // <<"test">>
+        116, 101, 115, 116,
+        98, 0, 0, 0, 4,                      // 4
+
+        100, 0, 5, 118, 97, 108, 117, 101,   // atom "value"
+        98, 0, 0, 0, 5,                      // 5
+    ];
+    let result: BTreeMap<serde_bytes::ByteBuf, i32> = binary_to_term(&data).unwrap();
+    let mut expected = BTreeMap::new();
+    expected.insert(serde_bytes::ByteBuf::from(b"test".to_vec()), 4);
+    expected.insert(serde_bytes::ByteBuf::from(b"value".to_vec()), 5);
+    assert_eq!(expected, result);
+}
+
+#[test]
+fn test_deserialize_native_map_empty() {
+    let data = vec![ETF_VERSION, 116, 0, 0, 0, 0];
+    let result: HashMap<String, i32> = binary_to_term(&data).unwrap();
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_deserialize_native_struct() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User {
+        id: i32,
+        name: String,
+    }
+
+    // %{'__struct__' => 'Elixir.User', id => 7, name => "bob"}
+    let data = vec![
+        ETF_VERSION,
+        116,                                 // Map
+        0, 0, 0, 3,                          // arity 3
+
+        100, 0, 10, 95, 95, 115, 116, 114,   // atom "__struct__"
+        117, 99, 116, 95, 95,
+        100, 0, 11, 69, 108, 105, 120, 105,  // atom "Elixir.User"
+        114, 46, 85, 115, 101, 114,
+
+        100, 0, 2, 105, 100,                 // atom "id"
+        98, 0, 0, 0, 7,                      // 7
+
+        100, 0, 4, 110, 97, 109, 101,        // atom "name"
+        107, 0, 3, 98, 111, 98,              // "bob"
+    ];
+    let result: User = binary_to_term(&data).unwrap();
+    assert_eq!(User { id: 7, name: "bob".to_string() }, result);
+}
+
 #[test]
 fn test_deserialize_small_bignum_positive() {
     let data = vec![
@@ -264,6 +573,221 @@ fn test_deserialize_bert_regex() {
     assert_eq!(BertRegex::new("^c(a*)t$", vec![RegexOption::Caseless]), result);
 }
 
+#[test]
+fn test_deserialize_new_pid() {
+    let data = vec![
+        ETF_VERSION,
+        88,                                          // NewPid
+        119, 13, 110, 111, 110, 111, 100, 101, 64,   // small utf8 atom "nonode@nohost"
+        110, 111, 104, 111, 115, 116,
+        0, 0, 0, 85,                                 // id
+        0, 0, 0, 0,                                  // serial
+        0, 0, 0, 0                                   // creation
+    ];
+    let result: BertPid = binary_to_term(&data).unwrap();
+    assert_eq!(BertPid::new("nonode@nohost", 85, 0, 0), result);
+
+    // Echoed pid keeps the tag of the node atom
+    assert_eq!(data, term_to_binary(&result).unwrap());
+}
+
+#[test]
+fn test_deserialize_pid() {
+    let data = vec![
+        ETF_VERSION,
+        103,                                         // Pid
+        100, 0, 6, 97, 64, 104, 111, 115, 116,       // atom "a@host"
+        0, 0, 0, 40,                                 // id
+        0, 0, 0, 1,                                  // serial
+        2                                            // creation
+    ];
+    let result: BertPid = binary_to_term(&data).unwrap();
+    assert_eq!(BertPid::new("a@host", 40, 1, 2), result);
+
+    // Echoed pid is byte-exact with the received one
+    assert_eq!(data, term_to_binary(&result).unwrap());
+    assert_eq!(data, Term::from_bytes(&data).unwrap().to_bytes().unwrap());
+
+    // Creation which doesn't fit into PID_EXT anymore
+    let mut pid = result;
+    pid.creation = 256;
+    let data = term_to_binary(&pid).unwrap();
+    assert_eq!(vec![131u8, 88, 100, 0, 6], data[..5].to_vec());
+}
+
+#[test]
+fn test_deserialize_reply_to_tuple() {
+    let data = vec![
+        ETF_VERSION,
+        104, 2,                                      // SmallTuple, arity 2
+        100, 0, 8, 114, 101, 112, 108, 121,          // atom "reply_to"
+        95, 116, 111,
+        88,                                          // NewPid
+        100, 0, 6, 97, 64, 104, 111, 115, 116,       // atom "a@host"
+        0, 0, 0, 40,                                 // id
+        0, 0, 0, 0,                                  // serial
+        0, 0, 0, 3                                   // creation
+    ];
+    let (tag, pid): (String, BertPid) = binary_to_term(&data).unwrap();
+    assert_eq!("reply_to", tag);
+    assert_eq!(BertPid::new("a@host", 40, 0, 3), pid);
+}
+
+#[test]
+fn test_deserialize_pid_invalid_tag() {
+    let data = vec![ETF_VERSION, 98, 0, 0, 0, 1];
+    assert!(binary_to_term::<BertPid>(&data).is_err());
+}
+
+#[test]
+fn test_deserialize_newer_reference() {
+    let data = vec![
+        ETF_VERSION,
+        90,                                          // NewerReference
+        0, 3,                                        // number of id words
+        119, 6, 97, 64, 104, 111, 115, 116,          // small utf8 atom "a@host"
+        101, 187, 179, 128,                          // creation
+        0, 2, 94, 66,                                // id words
+        131, 252, 0, 2,
+        0, 0, 5, 239
+    ];
+    let result: BertReference = binary_to_term(&data).unwrap();
+    assert_eq!(
+        BertReference::new("a@host", 1706800000, vec![155202, 2214330370, 1519]),
+        result
+    );
+}
+
+#[test]
+fn test_deserialize_new_reference() {
+    let data = vec![
+        ETF_VERSION,
+        114,                                         // NewReference
+        0, 2,                                        // number of id words
+        100, 0, 6, 97, 64, 104, 111, 115, 116,       // atom "a@host"
+        3,                                           // creation
+        0, 0, 0, 1,                                  // id words
+        0, 0, 0, 2
+    ];
+    let result: BertReference = binary_to_term(&data).unwrap();
+    assert_eq!(BertReference::new("a@host", 3, vec![1, 2]), result);
+}
+
+#[test]
+fn test_deserialize_legacy_reference() {
+    let data = vec![
+        ETF_VERSION,
+        101,                                         // Reference
+        100, 0, 6, 97, 64, 104, 111, 115, 116,       // atom "a@host"
+        0, 0, 0, 42,                                 // id
+        1                                            // creation
+    ];
+    let result: BertReference = binary_to_term(&data).unwrap();
+    assert_eq!(BertReference::new("a@host", 1, vec![42]), result);
+}
+
+#[test]
+fn test_deserialize_port() {
+    let data = vec![
+        ETF_VERSION,
+        102,                                         // Port
+        100, 0, 6, 97, 64, 104, 111, 115, 116,       // atom "a@host"
+        0, 0, 0, 17,                                 // id
+        2                                            // creation
+    ];
+    let result: BertPort = binary_to_term(&data).unwrap();
+    assert_eq!(BertPort::new("a@host", 17, 2), result);
+}
+
+#[test]
+fn test_deserialize_new_port() {
+    let data = vec![
+        ETF_VERSION,
+        89,                                          // NewPort
+        119, 6, 97, 64, 104, 111, 115, 116,          // small utf8 atom "a@host"
+        0, 0, 0, 17,                                 // id
+        101, 187, 179, 128                           // creation
+    ];
+    let result: BertPort = binary_to_term(&data).unwrap();
+    assert_eq!(BertPort::new("a@host", 17, 1706800000), result);
+}
+
+#[test]
+fn test_deserialize_v4_port() {
+    let data = vec![
+        ETF_VERSION,
+        120,                                         // V4Port
+        119, 6, 97, 64, 104, 111, 115, 116,          // small utf8 atom "a@host"
+        0, 0, 0, 1, 0, 0, 0, 5,                      // id
+        0, 0, 0, 3                                   // creation
+    ];
+    let result: BertPort = binary_to_term(&data).unwrap();
+    assert_eq!(BertPort::new("a@host", 4294967301, 3), result);
+}
+
+#[test]
+fn test_deserialize_bitstring() {
+    // <<1, 2, 5:3>> in Erlang
+    let data = vec![ETF_VERSION, 77, 0, 0, 0, 3, 3, 1, 2, 160];
+    let result: BertBitString = binary_to_term(&data).unwrap();
+    assert_eq!(BertBitString::new(vec![1, 2, 160], 3), result);
+}
+
+#[test]
+fn test_deserialize_bitstring_from_binary() {
+    let data = vec![ETF_VERSION, 109, 0, 0, 0, 2, 1, 2];
+    let result: BertBitString = binary_to_term(&data).unwrap();
+    assert_eq!(BertBitString::new(vec![1, 2], 8), result);
+}
+
+#[test]
+fn test_deserialize_bitstring_as_bytes_rejected() {
+    let data = vec![ETF_VERSION, 77, 0, 0, 0, 1, 3, 160];
+    let result = binary_to_term::<Vec<u8>>(&data);
+    assert!(matches!(result, Err(Error::UnexpectedBitstring)));
+
+    let result = binary_to_term::<serde_bytes::ByteBuf>(&data);
+    assert!(matches!(result, Err(Error::UnexpectedBitstring)));
+}
+
+#[test]
+fn test_deserialize_bitstring_as_bytes_accepted() {
+    let options = DeserializerOptions {
+        accept_bitstrings: true,
+        ..DeserializerOptions::default()
+    };
+    let data = vec![ETF_VERSION, 77, 0, 0, 0, 2, 3, 1, 160];
+
+    let result: Vec<u8> = binary_to_term_with_options(&data, options.clone()).unwrap();
+    assert_eq!(vec![1, 160], result);
+
+    let result: serde_bytes::ByteBuf = binary_to_term_with_options(&data, options).unwrap();
+    assert_eq!(vec![1, 160], result.into_vec());
+}
+
+#[test]
+fn test_deserialize_bitstring_invalid_bits() {
+    let options = DeserializerOptions {
+        accept_bitstrings: true,
+        ..DeserializerOptions::default()
+    };
+    for bits in [0, 9, 12] {
+        let data = vec![ETF_VERSION, 77, 0, 0, 0, 2, bits, 1, 160];
+        assert!(matches!(
+            binary_to_term::<BertBitString>(&data),
+            Err(Error::InvalidBitCount(_))
+        ));
+        assert!(matches!(
+            binary_to_term_with_options::<Vec<u8>>(&data, options.clone()),
+            Err(Error::InvalidBitCount(_))
+        ));
+        assert!(matches!(Term::from_bytes(&data), Err(Error::InvalidBitCount(_))));
+    }
+
+    let data = vec![ETF_VERSION, 77, 0, 0, 0, 0, 0];
+    assert_eq!(BertBitString::new(vec![], 0), binary_to_term(&data).unwrap());
+}
+
 #[test]
 fn test_deserialize_option_none() {
     let data = vec![ETF_VERSION, 106]; // Nil
@@ -346,6 +870,55 @@ fn test_roundtrip_map() {
     assert_eq!(map, result);
 }
 
+#[test]
+fn test_roundtrip_native_map() {
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        ..SerializerOptions::default()
+    };
+    let mut map = HashMap::new();
+    map.insert("alpha".to_string(), 1i32);
+    map.insert("beta".to_string(), 2i32);
+    let data = term_to_binary_with_options(&map, options).unwrap();
+    let result: HashMap<String, i32> = binary_to_term(&data).unwrap();
+    assert_eq!(map, result);
+}
+
+#[test]
+fn test_roundtrip_native_struct() {
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        limits: BTreeMap<String, i32>,
+        point: (i32, i32),
+    }
+
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        ..SerializerOptions::default()
+    };
+    let mut limits = BTreeMap::new();
+    limits.insert("cpu".to_string(), 4);
+    let config = Config { name: "worker".to_string(), limits, point: (1, 2) };
+    let data = term_to_binary_with_options(&config, options).unwrap();
+    let result: Config = binary_to_term(&data).unwrap();
+    assert_eq!(config, result);
+}
+
+#[test]
+fn test_roundtrip_utf8_atom_keys() {
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct Ключ {
+        значение: i32,
+    }
+
+    let value = Ключ { значение: 5 };
+    let data = term_to_binary(&value).unwrap();
+    assert_eq!(118, data[6]); // struct name is written as ATOM_UTF8_EXT
+    let result: Ключ = binary_to_term(&data).unwrap();
+    assert_eq!(value, result);
+}
+
 #[test]
 fn test_roundtrip_struct() {
     #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
@@ -373,6 +946,46 @@ fn test_roundtrip_bignum() {
     assert_eq!(negative, result);
 }
 
+#[test]
+fn test_roundtrip_i128() {
+    for value in [0i128, -1, 255, i32::MIN as i128, i64::MAX as i128 + 1, i128::MIN, i128::MAX] {
+        let data = term_to_binary(&value).unwrap();
+        assert_eq!(value, binary_to_term::<i128>(&data).unwrap());
+    }
+}
+
+#[test]
+fn test_roundtrip_u128() {
+    for value in [0u128, 255, 256, u64::MAX as u128, i128::MAX as u128 + 1, u128::MAX] {
+        let data = term_to_binary(&value).unwrap();
+        assert_eq!(value, binary_to_term::<u128>(&data).unwrap());
+    }
+}
+
+#[test]
+fn test_deserialize_128bit_from_small_integers() {
+    let data = vec![ETF_VERSION, 97, 42];
+    assert_eq!(42u128, binary_to_term(&data).unwrap());
+
+    let data = vec![ETF_VERSION, 98, 255, 255, 255, 254];
+    assert_eq!(-2i128, binary_to_term(&data).unwrap());
+}
+
+#[test]
+fn test_roundtrip_64bit_struct() {
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct Event {
+        id: u64,
+        at: i64,
+        offset: i64,
+    }
+
+    let event = Event { id: u64::MAX, at: 1_700_000_000_000, offset: -5 };
+    let data = term_to_binary(&event).unwrap();
+    let result: Event = binary_to_term(&data).unwrap();
+    assert_eq!(event, result);
+}
+
 #[test]
 fn test_roundtrip_bert_time() {
     let time = BertTime::new(1255, 295581, 446228);
@@ -389,6 +1002,373 @@ fn test_roundtrip_bert_regex() {
     assert_eq!(regex, result);
 }
 
+#[test]
+fn test_roundtrip_pid() {
+    let pid = BertPid::new("Worker@Host", 1024, 7, 1706800000);
+    let data = term_to_binary(&pid).unwrap();
+    let result: BertPid = binary_to_term(&data).unwrap();
+    assert_eq!(pid, result);
+
+    // Echoed pid is byte-exact with the received one
+    let echoed = term_to_binary(&result).unwrap();
+    assert_eq!(data, echoed);
+}
+
+#[test]
+fn test_roundtrip_struct_with_pid() {
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct Request {
+        reply_to: BertPid,
+        payload: String,
+    }
+
+    let request = Request {
+        reply_to: BertPid::new("a@host", 40, 0, 3),
+        payload: "ping".to_string(),
+    };
+    let data = term_to_binary(&request).unwrap();
+    let result: Request = binary_to_term(&data).unwrap();
+    assert_eq!(request, result);
+}
+
+#[test]
+fn test_roundtrip_reference() {
+    let reference = BertReference::new("Worker@Host", 1706800000, vec![1, 2, 3, 4, 5]);
+    let data = term_to_binary(&reference).unwrap();
+    let result: BertReference = binary_to_term(&data).unwrap();
+    assert_eq!(reference, result);
+    assert_eq!(data, term_to_binary(&result).unwrap());
+}
+
+#[test]
+fn test_roundtrip_reference_as_correlation_id() {
+    let request = (String::from("call"), BertReference::new("a@host", 3, vec![7, 8, 9]));
+    let data = term_to_binary(&request).unwrap();
+    let result: (String, BertReference) = binary_to_term(&data).unwrap();
+    assert_eq!(request, result);
+}
+
+#[test]
+fn test_roundtrip_port() {
+    let port = BertPort::new("a@host", 17, 1706800000);
+    let data = term_to_binary(&port).unwrap();
+    assert_eq!(89, data[1]);
+    let result: BertPort = binary_to_term(&data).unwrap();
+    assert_eq!(port, result);
+    assert_eq!(data, term_to_binary(&result).unwrap());
+}
+
+#[test]
+fn test_roundtrip_v4_port() {
+    let port = BertPort::new("a@host", u64::MAX, 3);
+    let data = term_to_binary(&port).unwrap();
+    assert_eq!(120, data[1]);
+    let result: BertPort = binary_to_term(&data).unwrap();
+    assert_eq!(port, result);
+    assert_eq!(data, term_to_binary(&result).unwrap());
+}
+
+#[test]
+fn test_roundtrip_struct_with_ports() {
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct PortInfo {
+        name: String,
+        ports: Vec<BertPort>,
+    }
+
+    let info = PortInfo {
+        name: "tcp_inet".to_string(),
+        ports: vec![BertPort::new("a@host", 1, 0), BertPort::new("b@host", 1 << 40, 2)],
+    };
+    let data = term_to_binary(&info).unwrap();
+    let result: PortInfo = binary_to_term(&data).unwrap();
+    assert_eq!(info, result);
+}
+
+#[test]
+fn test_roundtrip_bitstring() {
+    let bitstring = BertBitString::new(vec![255, 128], 1);
+    let data = term_to_binary(&bitstring).unwrap();
+    let result: BertBitString = binary_to_term(&data).unwrap();
+    assert_eq!(bitstring, result);
+    assert_eq!(data, term_to_binary(&result).unwrap());
+}
+
+#[test]
+fn test_deserialize_export_fun() {
+    // fun mymod:run/1
+    let data = vec![
+        ETF_VERSION,
+        113,                                         // Export
+        119, 5, 109, 121, 109, 111, 100,             // small utf8 atom "mymod"
+        119, 3, 114, 117, 110,                       // small utf8 atom "run"
+        97, 1                                        // arity
+    ];
+    let result: BertExportFun = binary_to_term(&data).unwrap();
+    assert_eq!(BertExportFun::new("mymod", "run", 1), result);
+}
+
+#[test]
+fn test_deserialize_export_fun_invalid_arity() {
+    let data = vec![
+        ETF_VERSION,
+        113,                                         // Export
+        119, 5, 109, 121, 109, 111, 100,             // small utf8 atom "mymod"
+        119, 3, 114, 117, 110,                       // small utf8 atom "run"
+        98, 0, 0, 0, 1                               // arity as integer
+    ];
+    assert!(binary_to_term::<BertExportFun>(&data).is_err());
+}
+
+#[test]
+fn test_roundtrip_struct_with_export_fun() {
+    #[derive(Debug, PartialEq, serde::Serialize, Deserialize)]
+    struct Job {
+        id: u64,
+        callback: BertExportFun,
+    }
+
+    let job = Job { id: 7, callback: BertExportFun::new("mymod", "run", 1) };
+    let data = term_to_binary(&job).unwrap();
+    let result: Job = binary_to_term(&data).unwrap();
+    assert_eq!(job, result);
+}
+
+#[test]
+fn test_deserialize_closure() {
+    let data = vec![
+        ETF_VERSION,
+        112,                                         // NewFun
+        0, 0, 0, 69,                                 // size
+        1,                                           // arity
+        1, 2, 3, 4, 5, 6, 7, 8,                      // uniq
+        9, 10, 11, 12, 13, 14, 15, 16,
+        0, 0, 0, 0,                                  // index
+        0, 0, 0, 1,                                  // number of free variables
+        119, 1, 109,                                 // module "m"
+        97, 0,                                       // old index
+        98, 5, 6, 7, 8,                              // old uniq
+        88,                                          // NewPid
+        119, 13, 110, 111, 110, 111, 100, 101, 64,   // "nonode@nohost"
+        110, 111, 104, 111, 115, 116,
+        0, 0, 0, 85,                                 // id
+        0, 0, 0, 0,                                  // serial
+        0, 0, 0, 0,                                  // creation
+        97, 42                                       // free variable
+    ];
+    let result: BertClosure = binary_to_term(&data).unwrap();
+    assert_eq!(1, result.arity);
+    assert_eq!([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16], result.uniq);
+    assert_eq!(0, result.index);
+    assert_eq!(vec![119, 1, 109], result.module);
+    assert_eq!(vec![97, 0], result.old_index);
+    assert_eq!(vec![98, 5, 6, 7, 8], result.old_uniq);
+    assert_eq!(88, result.pid[0]);
+    assert_eq!(vec![vec![97, 42]], result.free_vars);
+}
+
+#[test]
+fn test_roundtrip_tuple_with_closure() {
+    let mut data = vec![
+        ETF_VERSION,
+        104, 2,                                      // SmallTuple, arity 2
+        97, 1,                                       // 1
+    ];
+    data.extend(vec![
+        112,                                         // NewFun
+        0, 0, 0, 69,                                 // size
+        1,                                           // arity
+        1, 2, 3, 4, 5, 6, 7, 8,                      // uniq
+        9, 10, 11, 12, 13, 14, 15, 16,
+        0, 0, 0, 0,                                  // index
+        0, 0, 0, 1,                                  // number of free variables
+        119, 1, 109,                                 // module "m"
+        97, 0,                                       // old index
+        98, 5, 6, 7, 8,                              // old uniq
+        88,                                          // NewPid
+        119, 13, 110, 111, 110, 111, 100, 101, 64,   // "nonode@nohost"
+        110, 111, 104, 111, 115, 116,
+        0, 0, 0, 85,                                 // id
+        0, 0, 0, 0,                                  // serial
+        0, 0, 0, 0,                                  // creation
+        97, 42                                       // free variable
+    ]);
+    let result: (u8, BertClosure) = binary_to_term(&data).unwrap();
+    assert_eq!(1, result.0);
+    assert_eq!(data, term_to_binary(&result).unwrap());
+}
+
+#[test]
+fn test_deserialize_closure_with_wrong_size() {
+    let mut data = vec![
+        ETF_VERSION,
+        112,                                         // NewFun
+        0, 0, 0, 69,                                 // size
+        1,                                           // arity
+        1, 2, 3, 4, 5, 6, 7, 8,                      // uniq
+        9, 10, 11, 12, 13, 14, 15, 16,
+        0, 0, 0, 0,                                  // index
+        0, 0, 0, 1,                                  // number of free variables
+        119, 1, 109,                                 // module "m"
+        97, 0,                                       // old index
+        98, 5, 6, 7, 8,                              // old uniq
+        88,                                          // NewPid
+        119, 13, 110, 111, 110, 111, 100, 101, 64,   // "nonode@nohost"
+        110, 111, 104, 111, 115, 116,
+        0, 0, 0, 85,                                 // id
+        0, 0, 0, 0,                                  // serial
+        0, 0, 0, 0,                                  // creation
+        97, 42                                       // free variable
+    ];
+    data[5] = 70;
+    data.push(0);
+    assert!(binary_to_term::<BertClosure>(&data).is_err());
+}
+
+#[test]
+fn test_deserialize_improper_list() {
+    // [1, 2 | 3]
+    let data = vec![
+        ETF_VERSION,
+        108, 0, 0, 0, 2,                             // List, length 2
+        97, 1,                                       // 1
+        97, 2,                                       // 2
+        97, 3                                        // tail
+    ];
+    let result: ImproperList<u8, u8> = binary_to_term(&data).unwrap();
+    assert_eq!(ImproperList::new(vec![1, 2], 3), result);
+}
+
+#[test]
+fn test_deserialize_iolist_with_binary_tail() {
+    // [<<"a">> | <<"bc">>]
+    let data = vec![
+        ETF_VERSION,
+        108, 0, 0, 0, 1,                             // List, length 1
+        109, 0, 0, 0, 1, 97,                         // <<"a">>
+        109, 0, 0, 0, 2, 98, 99                      // tail <<"bc">>
+    ];
+    let result: ImproperList<serde_bytes::ByteBuf, serde_bytes::ByteBuf> =
+        binary_to_term(&data).unwrap();
+    assert_eq!(1, result.elements.len());
+    assert_eq!(b"a".to_vec(), result.elements[0].to_vec());
+    assert_eq!(b"bc".to_vec(), result.tail.into_vec());
+}
+
+#[test]
+fn test_deserialize_proper_list_as_improper_list() {
+    let data = vec![
+        ETF_VERSION,
+        108, 0, 0, 0, 1,                             // List, length 1
+        97, 1,                                       // 1
+        106                                          // Nil
+    ];
+    let result: ImproperList<u8, ()> = binary_to_term(&data).unwrap();
+    assert_eq!(ImproperList::new(vec![1], ()), result);
+}
+
+#[test]
+fn test_deserialize_improper_list_as_vec() {
+    let data = vec![
+        ETF_VERSION,
+        108, 0, 0, 0, 2,                             // List, length 2
+        97, 1,                                       // 1
+        97, 2,                                       // 2
+        97, 3                                        // tail
+    ];
+    let result = binary_to_term::<Vec<u8>>(&data);
+    assert!(matches!(result, Err(Error::ImproperList)));
+}
+
+#[test]
+fn test_roundtrip_improper_list() {
+    let list = ImproperList::new(vec![1i32, -2, 300], 4u64);
+    let data = term_to_binary(&list).unwrap();
+    let result: ImproperList<i32, u64> = binary_to_term(&data).unwrap();
+    assert_eq!(list, result);
+}
+
+#[test]
+fn test_roundtrip_improper_list_without_elements() {
+    let list: ImproperList<u8, u8> = ImproperList::new(vec![], 5);
+    let data = term_to_binary(&list).unwrap();
+    assert_eq!(vec![ETF_VERSION, 97, 5], data);
+    let result: ImproperList<u8, u8> = binary_to_term(&data).unwrap();
+    assert_eq!(list, result);
+}
+
+#[test]
+fn test_deserialize_compressed() {
+    // term_to_binary(lists:duplicate(20, 1), [compressed])
+    let data = vec![
+        ETF_VERSION,
+        80,                                              // compressed
+        0, 0, 0, 46,                                     // uncompressed size
+        120, 156, 203, 97, 96, 96, 16, 73, 100, 36, 14,  // zlib data
+        102, 1, 0, 191, 164, 8, 147
+    ];
+    let result: Vec<u8> = binary_to_term(&data).unwrap();
+    assert_eq!(vec![1u8; 20], result);
+}
+
+#[test]
+fn test_deserialize_compressed_with_wrong_size() {
+    let data = vec![
+        ETF_VERSION,
+        80,                                              // compressed
+        0, 0, 0, 47,                                     // uncompressed size
+        120, 156, 203, 97, 96, 96, 16, 73, 100, 36, 14,  // zlib data
+        102, 1, 0, 191, 164, 8, 147
+    ];
+    assert!(binary_to_term::<Vec<u8>>(&data).is_err());
+}
+
+#[test]
+fn test_deserialize_compressed_with_trailing_bytes() {
+    let data = vec![
+        ETF_VERSION,
+        80,                                              // compressed
+        0, 0, 0, 46,                                     // uncompressed size
+        120, 156, 203, 97, 96, 96, 16, 73, 100, 36, 14,  // zlib data
+        102, 1, 0, 191, 164, 8, 147,
+        1, 2, 3, 4,                                      // junk
+    ];
+    assert!(matches!(binary_to_term::<Vec<u8>>(&data), Err(Error::TrailingBytes)));
+    assert!(matches!(Term::from_bytes(&data), Err(Error::TrailingBytes)));
+}
+
+#[test]
+fn test_roundtrip_compressed() {
+    let value = vec!["repeated".to_string(); 100];
+    let data = term_to_binary_compressed(&value, 6).unwrap();
+    assert_eq!(80, data[1]);
+    assert!(data.len() < term_to_binary(&value).unwrap().len());
+    let result: Vec<String> = binary_to_term(&data).unwrap();
+    assert_eq!(value, result);
+}
+
+#[test]
+fn test_roundtrip_long_string() {
+    let value = "строка ".repeat(10000);
+    let data = term_to_binary(&value).unwrap();
+    let result: String = binary_to_term(&data).unwrap();
+    assert_eq!(value, result);
+}
+
+#[test]
+fn test_roundtrip_legacy_floats() {
+    let options = SerializerOptions {
+        minor_version: MinorVersion::V0,
+        ..SerializerOptions::default()
+    };
+    for value in [0.1f64, -1.0e-300, 6.02214076e23, f64::MAX, 0.0] {
+        let data = term_to_binary_with_options(&value, options.clone()).unwrap();
+        assert_eq!(99, data[1]);
+        let result: f64 = binary_to_term(&data).unwrap();
+        assert_eq!(value, result);
+    }
+}
+
 #[test]
 fn test_roundtrip_option_none() {
     let val: Option<i32> = None;
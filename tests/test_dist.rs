@@ -0,0 +1,181 @@
+use bert::{
+    ETF_VERSION, AtomCache, DistFragment, BertPid, DeserializerOptions,
+    read_dist_header, from_dist_slice, to_dist_vec, from_slice_with_options
+};
+use serde::{Deserialize, Serialize};
+
+
+#[test]
+fn test_read_dist_header() {
+    let mut cache = AtomCache::new();
+    cache.insert(7, "net_kernel").unwrap();
+
+    let data = vec![
+        ETF_VERSION,
+        68,                                          // DistHeader
+        3,                                           // number of atom cache refs
+        137, 0,                                      // flags
+        5, 6, 97, 64, 104, 111, 115, 116,            // new entry 261: "a@host"
+        1, 0,                                        // new entry 1: ""
+        7,                                           // cached entry 7
+    ];
+    let mut reader = data.as_slice();
+    let header = read_dist_header(&mut reader, &mut cache).unwrap();
+
+    assert_eq!(None, header.fragment);
+    assert_eq!(vec!["a@host", "", "net_kernel"], header.atoms);
+    assert_eq!(Some("a@host"), cache.get(261));
+    assert_eq!(Some(""), cache.get(1));
+    assert!(reader.is_empty());
+}
+
+
+#[test]
+fn test_read_dist_frag_header() {
+    let mut cache = AtomCache::new();
+
+    let data = vec![
+        ETF_VERSION,
+        69,                                          // DistFragHeader
+        0, 0, 0, 0, 0, 0, 0, 9,                      // sequence id
+        0, 0, 0, 0, 0, 0, 0, 1,                      // fragment id
+        1,                                           // number of atom cache refs
+        8,                                           // flags
+        4, 2, 111, 107,                              // new entry 4: "ok"
+    ];
+    let mut reader = data.as_slice();
+    let header = read_dist_header(&mut reader, &mut cache).unwrap();
+
+    assert_eq!(Some(DistFragment { sequence_id: 9, fragment_id: 1 }), header.fragment);
+    assert_eq!(vec!["ok"], header.atoms);
+}
+
+
+#[test]
+fn test_read_dist_header_with_long_atoms() {
+    let mut cache = AtomCache::new();
+
+    let data = vec![
+        ETF_VERSION,
+        68,                                          // DistHeader
+        1,                                           // number of atom cache refs
+        24,                                          // flags, long atoms
+        0, 0, 1, 97,                                 // new entry 0: "a"
+    ];
+    let header = read_dist_header(&mut data.as_slice(), &mut cache).unwrap();
+    assert_eq!(vec!["a"], header.atoms);
+}
+
+
+#[test]
+fn test_read_dist_header_with_empty_cache_entry() {
+    let mut cache = AtomCache::new();
+
+    let data = vec![ETF_VERSION, 68, 1, 0, 7];
+    assert!(read_dist_header(&mut data.as_slice(), &mut cache).is_err());
+}
+
+
+#[test]
+fn test_from_dist_slice() {
+    let mut cache = AtomCache::new();
+    cache.insert(7, "net_kernel").unwrap();
+
+    // {6, Pid, '', net_kernel} control message with 42 as the payload
+    let data = vec![
+        ETF_VERSION,
+        68,                                          // DistHeader
+        3,                                           // number of atom cache refs
+        137, 0,                                      // flags
+        5, 6, 97, 64, 104, 111, 115, 116,            // new entry 261: "a@host"
+        1, 0,                                        // new entry 1: ""
+        7,                                           // cached entry 7
+        104, 4,                                      // SmallTuple, arity 4
+        97, 6,                                       // 6
+        88,                                          // NewPid
+        82, 0,                                       // atom cache ref 0
+        0, 0, 0, 85,                                 // id
+        0, 0, 0, 0,                                  // serial
+        0, 0, 0, 3,                                  // creation
+        82, 1,                                       // atom cache ref 1
+        82, 2,                                       // atom cache ref 2
+        97, 42                                       // payload
+    ];
+    let (header, control, message): (_, (u8, BertPid, String, String), Option<u8>) =
+        from_dist_slice(&data, &mut cache).unwrap();
+
+    assert_eq!(3, header.atoms.len());
+    assert_eq!(
+        (6, BertPid::new("a@host", 85, 0, 3), "".to_string(), "net_kernel".to_string()),
+        control
+    );
+    assert_eq!(Some(42), message);
+}
+
+
+#[test]
+fn test_from_dist_slice_without_payload() {
+    let mut cache = AtomCache::new();
+
+    let data = vec![ETF_VERSION, 68, 0, 104, 1, 97, 1];
+    let (_, control, message): (_, (u8,), Option<u8>) =
+        from_dist_slice(&data, &mut cache).unwrap();
+    assert_eq!((1,), control);
+    assert_eq!(None, message);
+}
+
+
+#[test]
+fn test_deserialize_atom_cache_ref() {
+    let options = DeserializerOptions {
+        atom_cache_refs: vec!["ok".to_string()],
+        ..DeserializerOptions::default()
+    };
+
+    let data = vec![ETF_VERSION, 82, 0];
+    let result: String = from_slice_with_options(&data, options.clone()).unwrap();
+    assert_eq!("ok", result);
+
+    let data = vec![ETF_VERSION, 82, 1];
+    assert!(from_slice_with_options::<String>(&data, options).is_err());
+}
+
+
+#[test]
+fn test_to_dist_vec() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Request {
+        id: u8,
+    }
+
+    let mut cache = AtomCache::new();
+    let pid = BertPid::new("a@host", 85, 0, 3);
+    let data = to_dist_vec(&(6u8, &pid), Some(&Request { id: 1 }), &mut cache).unwrap();
+
+    assert_eq!(vec![ETF_VERSION, 68, 3], data[..3].to_vec());
+    // Every atom is a new cache entry with the text
+    assert_eq!(0x88, data[3] & 0x88);
+    assert_eq!(0x08, data[4] & 0x08);
+    assert!(cache.index_of("a@host").is_some());
+    assert!(cache.index_of("request").is_some());
+    assert!(cache.index_of("id").is_some());
+
+    let mut peer_cache = AtomCache::new();
+    let (header, control, message): (_, (u8, BertPid), Option<Request>) =
+        from_dist_slice(&data, &mut peer_cache).unwrap();
+    assert_eq!(vec!["a@host", "request", "id"], header.atoms);
+    assert_eq!((6, pid.clone()), control);
+    assert_eq!(Some(Request { id: 1 }), message);
+
+    // The next message only refers to the cached atoms
+    let next = to_dist_vec(&(6u8, &pid), None::<&()>, &mut cache).unwrap();
+    let index = cache.index_of("a@host").unwrap();
+    assert_eq!(
+        vec![ETF_VERSION, 68, 1, (index / 256) as u8, (index % 256) as u8],
+        next[..5].to_vec()
+    );
+    let (_, control, message): (_, (u8, BertPid), Option<()>) =
+        from_dist_slice(&next, &mut peer_cache).unwrap();
+    assert_eq!((6, pid), control);
+    assert_eq!(None, message);
+}
@@ -0,0 +1,173 @@
+use bert::{
+    Term, BertPid, BertBitString, BertExportFun, Error, Syntax,
+    parse_term_with_syntax, consult_with_syntax, text_to_binary_with_syntax, from_slice, term
+};
+use serde::Deserialize;
+
+
+fn elixir(term: &Term) -> String {
+    term.to_string_with_syntax(Syntax::Elixir)
+}
+
+
+fn parse(text: &str) -> Term {
+    parse_term_with_syntax(text, Syntax::Elixir).unwrap()
+}
+
+
+#[test]
+fn test_write_elixir() {
+    let atom = |name: &str| elixir(&Term::Atom(name.to_string()));
+    assert_eq!(":ok", atom("ok"));
+    assert_eq!(":valid?", atom("valid?"));
+    assert_eq!(":\"hello world\"", atom("hello world"));
+    assert_eq!("true", atom("true"));
+    assert_eq!("nil", atom("nil"));
+    assert_eq!("Foo.Bar", atom("Elixir.Foo.Bar"));
+    assert_eq!(":\"Elixir.foo\"", atom("Elixir.foo"));
+
+    assert_eq!("\"héllo\\n\"", elixir(&term!(<<"héllo\n">>)));
+    assert_eq!("\"\\#{x}\"", elixir(&term!(<<"#{x}">>)));
+    assert_eq!("<<255, 0>>", elixir(&term!(<<255, 0>>)));
+    assert_eq!("'abc'", elixir(&term!("abc")));
+    assert_eq!("[1, 2, 3]", elixir(&term!([1, 2, 3])));
+    assert_eq!("[]", elixir(&term!([])));
+    assert_eq!("[:a | :b]", elixir(&term!([a | b])));
+    assert_eq!("{:ok, 1.5}", elixir(&term!({ok, 1.5})));
+    assert_eq!("[nan, inf, -inf]", elixir(&Term::List(vec![
+        Term::Float(f64::NAN),
+        Term::Float(f64::INFINITY),
+        Term::Float(f64::NEG_INFINITY),
+    ])));
+    assert!(parse_term_with_syntax("nan", Syntax::Elixir).is_err());
+    assert_eq!("[timeout: 5000, \"max age\": 60]", elixir(&Term::List(vec![
+        term!({timeout, 5000}),
+        Term::Tuple(vec![Term::Atom("max age".to_string()), Term::Integer(60)]),
+    ])));
+
+    assert_eq!("%{}", elixir(&term!(#{})));
+    assert_eq!("%{id: 42, name: \"bob\"}", elixir(&term!(#{name => <<"bob">>, id => 42})));
+    assert_eq!("%{1 => :a, :b => 2}", elixir(&term!(#{b => 2, 1 => a})));
+    let user = Term::Map(vec![
+        (term!(__struct__), Term::Atom("Elixir.MyApp.User".to_string())),
+        (term!(name), term!(<<"bob">>)),
+    ]);
+    assert_eq!("%MyApp.User{name: \"bob\"}", elixir(&user));
+
+    assert_eq!("#PID<0.85.0>", elixir(&Term::Pid(BertPid::new("nonode@nohost", 85, 0, 0))));
+    assert_eq!(
+        "&Enum.map/2",
+        elixir(&Term::ExportFun(BertExportFun::new("Elixir.Enum", "map", 2)))
+    );
+    assert_eq!(
+        "&:lists.reverse/1",
+        elixir(&Term::ExportFun(BertExportFun::new("lists", "reverse", 1)))
+    );
+}
+
+
+#[test]
+fn test_write_elixir_pretty() {
+    let term = term!{ {reply, [{id, 42}, {name, <<"bob">>}], #{active => true}} };
+    assert_eq!(
+        "{:reply, [id: 42, name: \"bob\"], %{active: true}}",
+        term.to_pretty_string_with_syntax(80, Syntax::Elixir)
+    );
+    assert_eq!(
+        concat!(
+            "{\n",
+            "  :reply,\n",
+            "  [id: 42, name: \"bob\"],\n",
+            "  %{active: true}\n",
+            "}",
+        ),
+        term.to_pretty_string_with_syntax(30, Syntax::Elixir)
+    );
+    assert_eq!(
+        concat!(
+            "{\n",
+            "  :reply,\n",
+            "  [\n",
+            "    id: 42,\n",
+            "    name: \"bob\"\n",
+            "  ],\n",
+            "  %{active: true}\n",
+            "}",
+        ),
+        term.to_pretty_string_with_syntax(20, Syntax::Elixir)
+    );
+}
+
+
+#[test]
+fn test_parse_elixir() {
+    assert_eq!(term!(ok), parse(":ok"));
+    assert_eq!(Term::Atom("hello world".to_string()), parse(":\"hello world\""));
+    assert_eq!(term!(true), parse("true"));
+    assert_eq!(term!(nil), parse("nil"));
+    assert_eq!(Term::Atom("Elixir.Foo.Bar".to_string()), parse("Foo.Bar"));
+    assert_eq!(Term::Integer(255), parse("0xFF"));
+    assert_eq!(Term::Integer(-1_000), parse("-1_000"));
+    assert_eq!(Term::Integer(97), parse("?a"));
+    assert_eq!(Term::Float(1.5), parse("1.5"));
+
+    assert_eq!(Term::Binary("héllo\n".as_bytes().to_vec()), parse("\"héllo\\n\""));
+    assert_eq!(term!(<<"ab", 1>>), parse("<<\"ab\", 1>>"));
+    assert_eq!(term!("abc"), parse("'abc'"));
+    let bitstring = Term::BitString(BertBitString::new(vec![1, 240], 4));
+    assert_eq!("<<1, 15::size(4)>>", elixir(&bitstring));
+    assert_eq!(bitstring, parse("<<1, 15::size(4)>>"));
+    assert_eq!(bitstring, parse("<<1, 15::4>>"));
+    assert_eq!(term!([a | b]), parse("[:a | :b]"));
+    assert_eq!(term!([{timeout, 5000}, {retry, true}]), parse("[timeout: 5000, retry: true]"));
+    assert_eq!(term!([1, {a, 2}]), parse("[1, a: 2]"));
+    assert_eq!(term!(#{id => 42, <<"k">> => 1}), parse("%{id: 42, \"k\" => 1}"));
+    assert_eq!(
+        Term::Map(vec![
+            (term!(__struct__), Term::Atom("Elixir.MyApp.User".to_string())),
+            (term!(name), term!(<<"bob">>)),
+        ]),
+        parse("%MyApp.User{name: \"bob\"}")
+    );
+    assert_eq!(
+        Term::ExportFun(BertExportFun::new("Elixir.Enum", "map", 2)),
+        parse("&Enum.map/2")
+    );
+
+    assert!(matches!(parse_term_with_syntax("x", Syntax::Elixir), Err(Error::Syntax { .. })));
+    assert!(matches!(
+        parse_term_with_syntax("\"#{x}\"", Syntax::Elixir),
+        Err(Error::Syntax { .. })
+    ));
+    assert_eq!(
+        vec![term!(ok), term!({a, 1})],
+        consult_with_syntax("# comment\n:ok\n{:a, 1}\n", Syntax::Elixir).unwrap()
+    );
+}
+
+
+#[test]
+fn test_elixir_roundtrip() {
+    let term = term!{
+        {reply, [{id, 42}, {name, <<"bob">>}], #{active => true, <<"k">> => [1, 2 | x]}, "chars"}
+    };
+    assert_eq!(term, parse(&elixir(&term)));
+    assert_eq!(term, parse(&term.to_pretty_string_with_syntax(20, Syntax::Elixir)));
+}
+
+
+#[test]
+fn test_elixir_text_to_binary() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        port: u16,
+        name: String,
+    }
+
+    let bytes = text_to_binary_with_syntax(
+        "%{port: 8080, name: 'server'}",
+        Syntax::Elixir
+    ).unwrap();
+    let config: Config = from_slice(&bytes).unwrap();
+    assert_eq!(Config { port: 8080, name: "server".to_string() }, config);
+}
@@ -0,0 +1,111 @@
+use std::cmp::Ordering;
+
+use bert::{
+    Term, BertBitString, BertPid, BertPort, BertReference,
+    compare_terms, get_atom, get_integer, get_nil, get_small_tuple
+};
+
+
+#[test]
+fn test_compare_types() {
+    let atom = get_atom("a");
+    let integer = get_integer(1000);
+    let tuple = get_small_tuple(0, vec![]);
+    let nil = get_nil();
+    let list = vec![107, 0, 1, 1];
+    let binary = vec![109, 0, 0, 0, 0];
+
+    // number < atom < tuple < nil < list < binary
+    let ordered = [integer, atom, tuple, nil, list, binary];
+    for (index, left) in ordered.iter().enumerate() {
+        for (other, right) in ordered.iter().enumerate() {
+            assert_eq!(index.cmp(&other), compare_terms(left, right).unwrap());
+        }
+    }
+}
+
+
+#[test]
+fn test_compare_numbers() {
+    let one = get_integer(1);
+    let float_one = vec![70, 63, 240, 0, 0, 0, 0, 0, 0];
+    let float_half = vec![70, 63, 224, 0, 0, 0, 0, 0, 0];
+    let big = get_integer(1 << 40);
+
+    assert_eq!(Ordering::Equal, compare_terms(&one, &float_one).unwrap());
+    assert_eq!(Ordering::Greater, compare_terms(&one, &float_half).unwrap());
+    assert_eq!(Ordering::Less, compare_terms(&get_integer(-1), &one).unwrap());
+    assert_eq!(Ordering::Less, compare_terms(&float_one, &big).unwrap());
+}
+
+
+#[test]
+fn test_compare_tuples_by_size_first() {
+    let pair = get_small_tuple(2, vec![97, 1, 97, 1]);
+    let triple = get_small_tuple(3, vec![97, 0, 97, 0, 97, 0]);
+    assert_eq!(Ordering::Less, compare_terms(&pair, &triple).unwrap());
+
+    let other_pair = get_small_tuple(2, vec![97, 1, 97, 2]);
+    assert_eq!(Ordering::Less, compare_terms(&pair, &other_pair).unwrap());
+}
+
+
+#[test]
+fn test_compare_lists() {
+    // [1, 2] < [1, 2, 3] < [1, 3]
+    let short = vec![107, 0, 2, 1, 2];
+    let long = vec![108, 0, 0, 0, 3, 97, 1, 97, 2, 97, 3, 106];
+    let greater = vec![107, 0, 2, 1, 3];
+    assert_eq!(Ordering::Less, compare_terms(&short, &long).unwrap());
+    assert_eq!(Ordering::Less, compare_terms(&long, &greater).unwrap());
+
+    // [1 | a] is compared with [1, 2] by the tail: a < [2]
+    let improper = vec![108, 0, 0, 0, 1, 97, 1, 100, 0, 1, 97];
+    assert_eq!(Ordering::Less, compare_terms(&improper, &short).unwrap());
+}
+
+
+#[test]
+fn test_compare_binaries() {
+    let short = vec![109, 0, 0, 0, 1, 1];
+    let long = vec![109, 0, 0, 0, 2, 1, 0];
+    assert_eq!(Ordering::Less, compare_terms(&short, &long).unwrap());
+
+    // <<1, 1:1>> goes after both <<1>> and <<1, 0>>
+    let bits = vec![77, 0, 0, 0, 2, 1, 1, 128];
+    assert_eq!(Ordering::Greater, compare_terms(&bits, &short).unwrap());
+    assert_eq!(Ordering::Greater, compare_terms(&bits, &long).unwrap());
+
+    // Unused bits of the last byte are ignored
+    let dirty_bits = vec![77, 0, 0, 0, 2, 1, 1, 255];
+    assert_eq!(Ordering::Equal, compare_terms(&bits, &dirty_bits).unwrap());
+}
+
+
+#[test]
+fn test_compare_invalid_bitstrings() {
+    let bits = |bytes: Vec<u8>, bits| Term::BitString(BertBitString::new(bytes, bits));
+    let binary = Term::Binary(vec![1, 2]);
+    assert_eq!(Ordering::Equal, bits(vec![1, 2], 12).compare(&binary));
+    assert_eq!(Ordering::Less, bits(vec![], 12).compare(&binary));
+    assert_eq!(Ordering::Less, bits(vec![], 0).compare(&binary));
+
+    // Decoded bitstrings with such bit counts are rejected
+    let data = vec![77, 0, 0, 0, 2, 12, 1, 2];
+    assert!(compare_terms(&data, &[109, 0, 0, 0, 0]).is_err());
+}
+
+
+#[test]
+fn test_compare_node_creation() {
+    // Identifiers from a restarted node go after the old ones
+    let pid = |creation| Term::Pid(BertPid::new("a@host", 85, 0, creation));
+    assert_eq!(Ordering::Less, pid(1).compare(&pid(2)));
+    assert_eq!(Ordering::Equal, pid(2).compare(&pid(2)));
+
+    let port = |creation| Term::Port(BertPort::new("a@host", 17, creation));
+    assert_eq!(Ordering::Less, port(1).compare(&port(2)));
+
+    let reference = |creation| Term::Reference(BertReference::new("a@host", creation, vec![1]));
+    assert_eq!(Ordering::Less, reference(1).compare(&reference(2)));
+}
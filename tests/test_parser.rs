@@ -0,0 +1,167 @@
+use bert::{
+    ETF_VERSION, Term, BertBitString, BertExportFun, Error,
+    parse_term, consult, text_to_binary, from_slice, term
+};
+use num::bigint::BigInt;
+use serde::Deserialize;
+
+
+#[test]
+fn test_parse_term() {
+    let term = parse_term("{reply, [{id, 42}, {name, <<\"bob\">>}], #{active => true}}.").unwrap();
+    assert_eq!(
+        term!{ {reply, [{id, 42}, {name, <<"bob">>}], #{active => true}} },
+        term
+    );
+    assert_eq!(term, term.to_string().parse().unwrap());
+}
+
+
+#[test]
+fn test_parse_atoms() {
+    let atom = |name: &str| Term::Atom(name.to_string());
+
+    assert_eq!(atom("ok"), parse_term("ok").unwrap());
+    assert_eq!(atom("node@host_1"), parse_term("node@host_1").unwrap());
+    assert_eq!(atom("Hello world"), parse_term("'Hello world'").unwrap());
+    assert_eq!(atom("it's\n"), parse_term("'it\\'s\\n'").unwrap());
+    assert_eq!(atom("case"), parse_term("'case'").unwrap());
+    assert_eq!(atom("ключ"), parse_term("'ключ'").unwrap());
+    assert!(matches!(parse_term("case"), Err(Error::Syntax { .. })));
+    assert!(matches!(parse_term("Variable"), Err(Error::Syntax { .. })));
+}
+
+
+#[test]
+fn test_parse_numbers() {
+    assert_eq!(Term::Integer(42), parse_term("42").unwrap());
+    assert_eq!(Term::Integer(-42), parse_term("- 42").unwrap());
+    assert_eq!(Term::Integer(1_000_000), parse_term("1_000_000").unwrap());
+    assert_eq!(Term::Integer(255), parse_term("16#FF").unwrap());
+    assert_eq!(Term::Integer(-10), parse_term("-2#1010").unwrap());
+    assert_eq!(Term::Integer(35), parse_term("36#z").unwrap());
+    assert_eq!(Term::Integer(97), parse_term("$a").unwrap());
+    assert_eq!(Term::Integer(10), parse_term("$\\n").unwrap());
+    assert_eq!(
+        Term::BigInteger(BigInt::from(2).pow(64)),
+        parse_term("18446744073709551616").unwrap()
+    );
+    assert_eq!(Term::Float(1.5), parse_term("1.5").unwrap());
+    assert_eq!(Term::Float(-0.025), parse_term("-2.5e-2").unwrap());
+    assert_eq!(Term::Float(1.0e10), parse_term("1.0E10").unwrap());
+    assert!(matches!(parse_term("37#1"), Err(Error::Syntax { .. })));
+    assert!(matches!(parse_term("1."), Ok(Term::Integer(1))));
+}
+
+
+#[test]
+fn test_parse_strings_and_binaries() {
+    assert_eq!(Term::String(b"abc".to_vec()), parse_term("\"abc\"").unwrap());
+    assert_eq!(Term::String(b"abcdef".to_vec()), parse_term("\"abc\" \"def\"").unwrap());
+    assert_eq!(Term::String(vec![99, 97, 102, 233]), parse_term("\"café\"").unwrap());
+    assert_eq!(
+        Term::List(vec![Term::Integer(1082), Term::Integer(1102)]),
+        parse_term("\"кю\"").unwrap()
+    );
+    assert_eq!(Term::List(vec![]), parse_term("\"\"").unwrap());
+    assert_eq!(Term::String(vec![1, 9, 127]), parse_term("\"\\1\\t\\x7f\"").unwrap());
+
+    // Lists of bytes are the same terms as strings
+    assert_eq!(parse_term("\"hi\"").unwrap(), parse_term("[104, 105]").unwrap());
+    assert_ne!(parse_term("\"hi\"").unwrap(), parse_term("[104, 105.0]").unwrap());
+
+    assert_eq!(Term::Binary(vec![]), parse_term("<<>>").unwrap());
+    assert_eq!(Term::Binary(b"ab\x01\x00".to_vec()), parse_term("<<\"ab\", 1, 256>>").unwrap());
+    assert_eq!(
+        Term::Binary("café".as_bytes().to_vec()),
+        parse_term("<<\"café\"/utf8>>").unwrap()
+    );
+
+    // Bitstrings are read back as they are printed
+    let bitstring = Term::BitString(BertBitString::new(vec![1, 240], 4));
+    assert_eq!("<<1,15:4>>", bitstring.to_string());
+    assert_eq!(bitstring, parse_term("<<1, 15:4>>").unwrap());
+    assert_eq!(Term::Binary(vec![255]), parse_term("<<255:8>>").unwrap());
+    assert!(matches!(parse_term("<<1:4, 2>>"), Err(Error::Syntax { .. })));
+    assert!(matches!(parse_term("<<1:9>>"), Err(Error::Syntax { .. })));
+}
+
+
+#[test]
+fn test_parse_containers() {
+    assert_eq!(term!({}), parse_term("{}").unwrap());
+    assert_eq!(term!([]), parse_term("[ ]").unwrap());
+    assert_eq!(term!(#{}), parse_term("#{}").unwrap());
+    assert_eq!(term!([a, b | c]), parse_term("[a, b | c]").unwrap());
+    assert_eq!(term!([a, b, c]), parse_term("[a | [b, c]]").unwrap());
+    assert_eq!(term!([a, 98, 99]), parse_term("[a | \"bc\"]").unwrap());
+    assert_eq!(
+        term!(#{a => 2, {b} => [1]}),
+        parse_term("#{a => 1, {b} => [1], a => 2}").unwrap()
+    );
+    // Keys are the same when they are exactly equal
+    assert_eq!(term!(#{"ab" => 2}), parse_term("#{\"ab\" => 1, [97, 98] => 2}").unwrap());
+    assert_eq!(term!(#{1 => a, 1.0 => b}), parse_term("#{1 => a, 1.0 => b}").unwrap());
+    assert_eq!(
+        Term::ExportFun(BertExportFun::new("lists", "reverse", 1)),
+        parse_term("fun lists:reverse/1").unwrap()
+    );
+}
+
+
+#[test]
+fn test_consult() {
+    let text = "
+        %% Server settings
+        {port, 8080}.   % the default port
+        {hosts, [\"localhost\", '127.0.0.1']}.
+        {ratio, 0.5}.
+    ";
+    let terms = consult(text).unwrap();
+
+    assert_eq!(
+        vec![
+            term!({port, 8080}),
+            term!({hosts, ["localhost", (Term::Atom("127.0.0.1".to_string()))]}),
+            term!({ratio, 0.5}),
+        ],
+        terms
+    );
+    assert!(consult("").unwrap().is_empty());
+
+    match consult("{port, 8080}.\n{hosts [a]}.") {
+        Err(Error::Syntax { line, column, .. }) => assert_eq!((2, 8), (line, column)),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(matches!(consult("{port, 8080}"), Err(Error::Syntax { .. })));
+    assert!(matches!(consult("{a}.{b}."), Err(Error::Syntax { .. })));
+}
+
+
+#[test]
+fn test_text_to_binary() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        port: u16,
+        name: String,
+    }
+
+    let bytes = text_to_binary("{port, 8080}.").unwrap();
+    assert_eq!(
+        vec![
+            ETF_VERSION,
+            104, 2,                                  // SmallTuple, arity 2
+            119, 4, 112, 111, 114, 116,              // port
+            98, 0, 0, 31, 144,                       // 8080
+        ],
+        bytes
+    );
+
+    // Lists of bytes are written as STRING_EXT, as Erlang does
+    let bytes = text_to_binary("[1, 2, 3].").unwrap();
+    assert_eq!(vec![ETF_VERSION, 107, 0, 3, 1, 2, 3], bytes);
+
+    let bytes = text_to_binary("#{port => 8080, name => \"server\"}").unwrap();
+    let config: Config = from_slice(&bytes).unwrap();
+    assert_eq!(Config { port: 8080, name: "server".to_string() }, config);
+}
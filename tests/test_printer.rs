@@ -0,0 +1,120 @@
+use bert::{Term, BertPid, BertReference, BertBitString, BertExportFun, parse_term, term};
+use num::bigint::BigInt;
+
+
+#[test]
+fn test_display_term() {
+    let term = term!{ {reply, [{id, 42}, {name, <<"bob">>}]} };
+    assert_eq!("{reply,[{id,42},{name,<<\"bob\">>}]}", term.to_string());
+
+    let term = term!{ #{b => [1, 2, 3], a => "abc", 1.5 => [a | b], 2 => <<1, 2>>} };
+    assert_eq!("#{2 => <<1,2>>,1.5 => [a|b],a => \"abc\",b => [1,2,3]}", term.to_string());
+
+    assert_eq!("[]", term!([]).to_string());
+    assert_eq!("<<>>", term!(<<>>).to_string());
+    assert_eq!("\"a\\\"b\\n\"", term!("a\"b\n").to_string());
+    assert_eq!("[104,105,0]", term!("hi\0").to_string());
+    assert_eq!("\"hi\"", term!([104, 105]).to_string());
+    assert_eq!(
+        "-18446744073709551616",
+        Term::BigInteger(BigInt::from(-2i128.pow(64))).to_string()
+    );
+}
+
+
+#[test]
+fn test_display_atom() {
+    let atom = |name: &str| Term::Atom(name.to_string()).to_string();
+
+    assert_eq!("ok", atom("ok"));
+    assert_eq!("node@host_1", atom("node@host_1"));
+    assert_eq!("café", atom("café"));
+    assert_eq!("'Ok'", atom("Ok"));
+    assert_eq!("'_ok'", atom("_ok"));
+    assert_eq!("'hello world'", atom("hello world"));
+    assert_eq!("'case'", atom("case"));
+    assert_eq!("'it\\'s'", atom("it's"));
+    assert_eq!("'a\\\\b\\001'", atom("a\\b\u{1}"));
+    assert_eq!("''", atom(""));
+    assert_eq!("'ключ'", atom("ключ"));
+}
+
+
+#[test]
+fn test_display_float() {
+    let float = |value: f64| Term::Float(value).to_string();
+
+    assert_eq!("1.5", float(1.5));
+    assert_eq!("-1.0", float(-1.0));
+    assert_eq!("0.0", float(0.0));
+    assert_eq!("1000.0", float(1000.0));
+    assert_eq!("1.0e4", float(10000.0));
+    assert_eq!("123456.0", float(123456.0));
+    assert_eq!("0.1", float(0.1));
+    assert_eq!("0.001", float(0.001));
+    assert_eq!("1.0e-10", float(1.0e-10));
+    assert_eq!("1.5e300", float(1.5e300));
+    assert_eq!("3.141592653589793", float(std::f64::consts::PI));
+
+    // NaN decoded from NEW_FLOAT_EXT and infinities have no syntax
+    let nan = Term::from_bytes(&[131, 70, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0]).unwrap();
+    assert_eq!("nan", nan.to_string());
+    assert_eq!("inf", float(f64::INFINITY));
+    assert_eq!("-inf", float(f64::NEG_INFINITY));
+    assert!(parse_term("-inf.").is_err());
+}
+
+
+#[test]
+fn test_display_erlang_types() {
+    let pid = BertPid::new("nonode@nohost", 85, 0, 0);
+    let reference = BertReference::new("nonode@nohost", 0, vec![25876, 1380450308, 3573411853]);
+    let fun = BertExportFun::new("lists", "reverse", 1);
+    let bitstring = BertBitString::new(vec![1, 64], 3);
+
+    let term = term!([(pid), (reference), (fun), (bitstring)]);
+    assert_eq!(
+        "[<0.85.0>,#Ref<0.3573411853.1380450308.25876>,fun lists:reverse/1,<<1,2:3>>]",
+        term.to_string()
+    );
+}
+
+
+#[test]
+fn test_pretty_print() {
+    let term = term!{
+        {reply, [{id, 42}, {name, <<"bob">>}, {roles, [admin, user]}], #{active => true}}
+    };
+
+    assert_eq!(term.to_string(), term.to_pretty_string(80));
+    assert_eq!(
+        concat!(
+            "{reply,[{id,42},\n",
+            "        {name,<<\"bob\">>},\n",
+            "        {roles,[admin,user]}],\n",
+            "       #{active => true}}",
+        ),
+        term.to_pretty_string(30)
+    );
+    assert_eq!(
+        concat!(
+            "{reply,[{id,42},\n",
+            "        {name,<<\"bob\">>},\n",
+            "        {roles,[admin,\n",
+            "                user]}],\n",
+            "       #{active => true}}",
+        ),
+        term.to_pretty_string(24)
+    );
+    assert_eq!(term.to_pretty_string(80), format!("{:#}", term));
+
+    let term = term!{ #{config => [{port, 8080}, {host, "localhost"}], name => <<"server">>} };
+    assert_eq!(
+        concat!(
+            "#{config => [{port,8080},\n",
+            "             {host,\"localhost\"}],\n",
+            "  name => <<\"server\">>}",
+        ),
+        term.to_pretty_string(40)
+    );
+}
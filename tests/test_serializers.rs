@@ -1,9 +1,16 @@
+#![allow(clippy::approx_constant)]
+
 use std::collections::{HashMap, BTreeMap};
 
 use bert::{
-    Serializer, term_to_binary,
-    BertTag, BertBigInteger, BertTime, BertRegex, RegexOption,
-    merge_terms, get_atom, get_nil, get_bert_nil, get_bert_atom
+    Serializer, SerializerOptions, MapEncoding, MinorVersion,
+    term_to_binary, term_to_binary_with_options,
+    term_to_binary_compressed, term_to_binary_compressed_with_options,
+    BertTag, BertBigInteger, BertTime, BertRegex, BertPid, BertReference, BertPort, BertBitString, BertExportFun,
+    BertClosure, ImproperList, RegexOption, Error,
+    merge_terms, str_to_binary, get_string,
+    get_atom, try_get_atom, get_exact_atom, get_utf8_atom, get_small_atom, get_small_utf8_atom,
+    get_nil, get_bert_nil, get_bert_atom
 };
 use num::bigint::BigInt;
 use serde::Serialize;
@@ -46,6 +53,51 @@ fn test_get_atom() {
 }
 
 
+#[test]
+fn test_get_atom_latin1() {
+    assert_eq!(
+        get_atom("café"),
+        vec![100u8, 0, 4, 99, 97, 102, 233]
+    );
+}
+
+
+#[test]
+fn test_get_atom_utf8() {
+    assert_eq!(
+        get_atom("ключ"),
+        vec![
+            118u8,                          // utf8 atom
+            0, 8,                           // length in bytes
+            208, 186, 208, 187,             // "кл"
+            209, 142, 209, 135              // "юч"
+        ]
+    );
+}
+
+
+#[test]
+fn test_get_atom_too_long() {
+    assert!(try_get_atom(&"a".repeat(255)).is_ok());
+    assert!(get_small_utf8_atom(&"☃".repeat(255)).is_ok());
+
+    // 70000 bytes would overflow the 16-bit length
+    let name = "a".repeat(70000);
+    assert!(matches!(try_get_atom(&name), Err(Error::AtomTooLong(_))));
+    assert!(matches!(get_exact_atom(&name), Err(Error::AtomTooLong(_))));
+    assert!(matches!(get_utf8_atom(&name), Err(Error::AtomTooLong(_))));
+    assert!(matches!(get_small_atom(&name), Err(Error::AtomTooLong(_))));
+    assert!(matches!(get_small_utf8_atom(&name), Err(Error::AtomTooLong(_))));
+}
+
+
+#[test]
+#[should_panic]
+fn test_get_atom_too_long_panics() {
+    get_atom(&"a".repeat(256));
+}
+
+
 #[test]
 fn test_get_nil() {
     assert_eq!(
@@ -177,9 +229,65 @@ fn test_serialize_i32() {
 
 
 #[test]
-#[should_panic]
 fn test_serialize_i64() {
-    term_to_binary(&1000i64).unwrap();
+    assert_eq!(
+        term_to_binary(&200i64).unwrap(),
+        vec![131u8, 97, 200]
+    );
+
+    assert_eq!(
+        term_to_binary(&1000i64).unwrap(),
+        vec![131u8, 98, 0, 0, 3, 232]
+    );
+
+    assert_eq!(
+        term_to_binary(&-1i64).unwrap(),
+        vec![131u8, 98, 255, 255, 255, 255]
+    );
+
+    assert_eq!(
+        term_to_binary(&2147483648i64).unwrap(),
+        vec![131u8, 110, 4, 0, 0, 0, 0, 128]
+    );
+
+    assert_eq!(
+        term_to_binary(&-2147483649i64).unwrap(),
+        vec![131u8, 110, 4, 1, 1, 0, 0, 128]
+    );
+
+    assert_eq!(
+        term_to_binary(&i64::MIN).unwrap(),
+        vec![131u8, 110, 8, 1, 0, 0, 0, 0, 0, 0, 0, 128]
+    );
+}
+
+
+#[test]
+fn test_serialize_i128() {
+    assert_eq!(
+        term_to_binary(&5i128).unwrap(),
+        vec![131u8, 97, 5]
+    );
+
+    assert_eq!(
+        term_to_binary(&-1000i128).unwrap(),
+        vec![131u8, 98, 255, 255, 252, 24]
+    );
+
+    assert_eq!(
+        term_to_binary(&-18446744073709551616i128).unwrap(),
+        vec![131u8, 110, 9, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+    );
+
+    assert_eq!(
+        term_to_binary(&i128::MIN).unwrap(),
+        vec![
+            131u8,
+            110, 16, 1,                     // small bignum, 16 bytes, negative
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 128
+        ]
+    );
 }
 
 
@@ -198,23 +306,73 @@ fn test_serialize_u8() {
 
 
 #[test]
-#[should_panic]
 fn test_serialize_u16() {
-    term_to_binary(&100u16).unwrap();
+    assert_eq!(
+        term_to_binary(&100u16).unwrap(),
+        vec![131u8, 97, 100]
+    );
+
+    assert_eq!(
+        term_to_binary(&65535u16).unwrap(),
+        vec![131u8, 98, 0, 0, 255, 255]
+    );
 }
 
 
 #[test]
-#[should_panic]
 fn test_serialize_u32() {
-    term_to_binary(&100u32).unwrap();
+    assert_eq!(
+        term_to_binary(&100u32).unwrap(),
+        vec![131u8, 97, 100]
+    );
+
+    assert_eq!(
+        term_to_binary(&2147483647u32).unwrap(),
+        vec![131u8, 98, 127, 255, 255, 255]
+    );
+
+    assert_eq!(
+        term_to_binary(&4294967295u32).unwrap(),
+        vec![131u8, 110, 4, 0, 255, 255, 255, 255]
+    );
 }
 
 
 #[test]
-#[should_panic]
 fn test_serialize_u64() {
-    term_to_binary(&100u64).unwrap();
+    assert_eq!(
+        term_to_binary(&100u64).unwrap(),
+        vec![131u8, 97, 100]
+    );
+
+    assert_eq!(
+        term_to_binary(&1000u64).unwrap(),
+        vec![131u8, 98, 0, 0, 3, 232]
+    );
+
+    assert_eq!(
+        term_to_binary(&u64::MAX).unwrap(),
+        vec![131u8, 110, 8, 0, 255, 255, 255, 255, 255, 255, 255, 255]
+    );
+}
+
+
+#[test]
+fn test_serialize_u128() {
+    assert_eq!(
+        term_to_binary(&255u128).unwrap(),
+        vec![131u8, 97, 255]
+    );
+
+    assert_eq!(
+        term_to_binary(&u128::MAX).unwrap(),
+        vec![
+            131u8,
+            110, 16, 0,                     // small bignum, 16 bytes, positive
+            255, 255, 255, 255, 255, 255, 255, 255,
+            255, 255, 255, 255, 255, 255, 255, 255
+        ]
+    );
 }
 
 
@@ -256,6 +414,200 @@ fn test_serialize_f64() {
 }
 
 
+#[test]
+fn test_serialize_f64_with_minor_version_0() {
+    let options = SerializerOptions {
+        minor_version: MinorVersion::V0,
+        ..SerializerOptions::default()
+    };
+
+    // term_to_binary(0.1, [{minor_version, 0}]) in Erlang
+    let mut expected = vec![131u8, 99];
+    expected.extend(b"1.00000000000000005551e-01");
+    expected.extend(vec![0; 5]);
+    assert_eq!(term_to_binary_with_options(&0.1f64, options.clone()).unwrap(), expected);
+
+    let mut expected = vec![131u8, 99];
+    expected.extend(b"-1.79769313486231570815e+308");
+    expected.extend(vec![0; 3]);
+    assert_eq!(term_to_binary_with_options(&f64::MIN, options.clone()).unwrap(), expected);
+
+    // Erlang can't read NaN and infinities
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(matches!(
+            term_to_binary_with_options(&value, options.clone()),
+            Err(Error::NonFiniteFloat(_))
+        ));
+    }
+}
+
+
+#[test]
+fn test_serialize_atoms_with_minor_version_2() {
+    let options = SerializerOptions {
+        minor_version: MinorVersion::V2,
+        ..SerializerOptions::default()
+    };
+
+    assert_eq!(
+        term_to_binary_with_options(&true, options).unwrap(),
+        vec![
+            131u8,
+            104,                            // small tuple tag
+            2,                              // tuple length
+            119, 4,  98, 101, 114, 116,     // "bert" as small utf8 atom
+            119, 4, 116, 114, 117, 101      // "true" as small utf8 atom
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_small_atoms() {
+    let options = SerializerOptions {
+        small_atoms: true,
+        ..SerializerOptions::default()
+    };
+
+    assert_eq!(
+        term_to_binary_with_options(&true, options.clone()).unwrap(),
+        vec![
+            131u8,
+            104,                            // small tuple tag
+            2,                              // tuple length
+            115, 4,  98, 101, 114, 116,     // "bert" as small atom
+            115, 4, 116, 114, 117, 101      // "true" as small atom
+        ]
+    );
+
+    let pid = BertPid::new("ß@host", 1, 0, 1);
+    let data = term_to_binary_with_options(&pid, options.clone()).unwrap();
+    assert_eq!(vec![131u8, 88, 115, 6, 223, 64, 104, 111, 115, 116], data[..10].to_vec());
+
+    let pid = BertPid::new("☃@host", 1, 0, 1);
+    let data = term_to_binary_with_options(&pid, options.clone()).unwrap();
+    assert_eq!(vec![131u8, 88, 119, 8, 226, 152, 131, 64], data[..8].to_vec());
+
+    // 255 characters, but longer than 255 bytes
+    let pid = BertPid::new(&"☃".repeat(255), 1, 0, 1);
+    let data = term_to_binary_with_options(&pid, options).unwrap();
+    assert_eq!(vec![131u8, 88, 118, 2, 253], data[..5].to_vec());
+}
+
+
+#[test]
+fn test_serialize_too_long_atom() {
+    let pid = BertPid::new(&"a".repeat(255), 1, 0, 1);
+    assert!(term_to_binary(&pid).is_ok());
+
+    let pid = BertPid::new(&"a".repeat(256), 1, 0, 1);
+    match term_to_binary(&pid) {
+        Err(Error::AtomTooLong(name)) => assert_eq!(256, name.len()),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+
+fn to_canonical<T: Serialize>(value: &T) -> Vec<u8> {
+    let options = SerializerOptions {
+        canonical: true,
+        ..SerializerOptions::default()
+    };
+    term_to_binary_with_options(value, options).unwrap()
+}
+
+
+#[test]
+fn test_serialize_canonical() {
+    #[derive(Serialize)]
+    struct Point(i32, i32);
+
+    #[derive(Serialize)]
+    enum Shape {
+        Rect(i32, i32),
+    }
+
+    // Expected values are the outputs of `erlang:term_to_binary`
+
+    // 1
+    assert_eq!(to_canonical(&1i32), vec![131u8, 97, 1]);
+    // -1
+    assert_eq!(to_canonical(&-1i32), vec![131u8, 98, 255, 255, 255, 255]);
+    // 256
+    assert_eq!(to_canonical(&256i16), vec![131u8, 98, 0, 0, 1, 0]);
+    // 2147483648
+    assert_eq!(to_canonical(&2147483648i64), vec![131u8, 110, 4, 0, 0, 0, 0, 128]);
+    // -2147483649
+    assert_eq!(to_canonical(&-2147483649i64), vec![131u8, 110, 4, 1, 1, 0, 0, 128]);
+    // {1, 2}
+    assert_eq!(to_canonical(&(1i32, 2i32)), vec![131u8, 104, 2, 97, 1, 97, 2]);
+    // {point, 1, 2}
+    assert_eq!(
+        to_canonical(&Point(1, 2)),
+        vec![131u8, 104, 3, 100, 0, 5, 112, 111, 105, 110, 116, 97, 1, 97, 2]
+    );
+    // {shape, {rect, 1, 2}}
+    assert_eq!(
+        to_canonical(&Shape::Rect(1, 2)),
+        vec![
+            131u8,
+            104, 2,                                  // small tuple, arity 2
+            100, 0, 5, 115, 104, 97, 112, 101,       // shape
+            104, 3,                                  // small tuple, arity 3
+            100, 0, 4, 114, 101, 99, 116,            // rect
+            97, 1,                                   // 1
+            97, 2                                    // 2
+        ]
+    );
+    // [1, 2, 3]
+    assert_eq!(to_canonical(&vec![1i32, 2, 3]), vec![131u8, 107, 0, 3, 1, 2, 3]);
+    // []
+    assert_eq!(to_canonical(&Vec::<i32>::new()), vec![131u8, 106]);
+    // [256, -1]
+    assert_eq!(
+        to_canonical(&vec![256i32, -1]),
+        vec![131u8, 108, 0, 0, 0, 2, 98, 0, 0, 1, 0, 98, 255, 255, 255, 255, 106]
+    );
+    // [{1, 2}]
+    assert_eq!(
+        to_canonical(&vec![(1i32, 2i32)]),
+        vec![131u8, 108, 0, 0, 0, 1, 104, 2, 97, 1, 97, 2, 106]
+    );
+    // [[1], []]
+    assert_eq!(
+        to_canonical(&vec![vec![1i32], vec![]]),
+        vec![131u8, 108, 0, 0, 0, 2, 107, 0, 1, 1, 106, 106]
+    );
+    // 'łódź' as the node of a pid
+    let data = to_canonical(&BertPid::new("łódź", 1, 0, 0));
+    assert_eq!(
+        vec![131u8, 88, 119, 7, 197, 130, 195, 179, 100, 197, 186],
+        data[..11].to_vec()
+    );
+    // café, which fits into Latin-1
+    let data = to_canonical(&BertPid::new("café", 1, 0, 0));
+    assert_eq!(vec![131u8, 88, 100, 0, 4, 99, 97, 102, 233], data[..9].to_vec());
+
+    // term_to_binary(abc, [{minor_version, 2}])
+    let options = SerializerOptions {
+        canonical: true,
+        minor_version: MinorVersion::V2,
+        ..SerializerOptions::default()
+    };
+    let data = term_to_binary_with_options(&BertPid::new("abc", 1, 0, 0), options).unwrap();
+    assert_eq!(vec![131u8, 88, 119, 3, 97, 98, 99], data[..7].to_vec());
+}
+
+
+#[test]
+fn test_serialize_canonical_roundtrip() {
+    let value = vec![vec![1u16, 2, 3], vec![], vec![1000]];
+    let data = to_canonical(&value);
+    let result: Vec<Vec<u16>> = bert::binary_to_term(&data).unwrap();
+    assert_eq!(value, result);
+}
+
+
 #[test]
 fn test_serialize_char() {
     assert_eq!(
@@ -274,6 +626,66 @@ fn test_serialize_string() {
 }
 
 
+#[test]
+fn test_serialize_string_with_max_length() {
+    let value = "a".repeat(40000);
+    let data = term_to_binary(&value).unwrap();
+
+    assert_eq!(data.len(), 4 + 40000);
+    assert_eq!(
+        data[..4].to_vec(),
+        vec![
+            131u8,
+            107,                                // string
+            156, 64                             // length
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_long_string() {
+    let value = "a".repeat(70000);
+    let data = term_to_binary(&value).unwrap();
+
+    assert_eq!(data.len(), 6 + 2 * 70000 + 1);
+    assert_eq!(
+        data[..8].to_vec(),
+        vec![
+            131u8,
+            108,                                // list
+            0, 1, 17, 112,                      // length
+            97, 97                              // 'a'
+        ]
+    );
+    assert_eq!(data[data.len() - 1], 106);      // nil
+}
+
+
+#[test]
+fn test_str_to_binary() {
+    assert_eq!(vec![0u8, 2, 111, 107], str_to_binary("ok"));
+    assert_eq!(65537, str_to_binary(&"a".repeat(65535)).len());
+}
+
+
+#[test]
+#[should_panic]
+fn test_str_to_binary_too_long() {
+    str_to_binary(&"a".repeat(70000));
+}
+
+
+#[test]
+fn test_get_string() {
+    assert_eq!(vec![107u8, 0, 2, 111, 107], get_string("ok"));
+
+    let binary = get_string(&"a".repeat(70000));
+    assert_eq!(vec![108u8, 0, 1, 17, 112, 97, 97], binary[..7].to_vec());
+    assert_eq!(5 + 2 * 70000 + 1, binary.len());
+}
+
+
 #[test]
 fn test_serialize_bytes() {
     let empty_bytes_list = serde_bytes::Bytes::new(b"");
@@ -577,6 +989,216 @@ fn test_serialize_map() {
 }
 
 
+#[test]
+fn test_serialize_native_map() {
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        ..SerializerOptions::default()
+    };
+
+    // empty map
+    let empty_map: HashMap<&str, i32> = HashMap::new();
+
+    assert_eq!(
+        term_to_binary_with_options(&empty_map, options.clone()).unwrap(),
+        vec![
+            131u8,
+            116,                                // map
+            0, 0, 0, 0                          // arity
+        ]
+    );
+
+    // map with few pairs of elements
+    let mut map: BTreeMap<&str, i32> = BTreeMap::new();
+    map.insert("test", 4);
+    map.insert("value", 5);
+
+    assert_eq!(
+        term_to_binary_with_options(&map, options).unwrap(),
+        vec![
+            131u8,
+            116,                                // map
+            0, 0, 0, 2,                         // arity
+
+            107, 0, 4, 116, 101, 115, 116,      // "test" as string
+            98, 0, 0, 0, 4,                     // 4
+
+            107, 0, 5, 118, 97, 108, 117, 101,  // "value" as string
+            98, 0, 0, 0, 5                      // 5
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_native_struct() {
+
+    #[derive(Serialize)]
+    struct Color {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+    let color = Color{r: 128, g: 128, b: 128};
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        ..SerializerOptions::default()
+    };
+
+    assert_eq!(
+        term_to_binary_with_options(&color, options).unwrap(),
+        vec![
+            131u8,
+            116,                                // map
+            0, 0, 0, 3,                         // arity
+
+            100, 0, 1, 114,                     // "r" as atom
+            97, 128,                            // 128
+
+            100, 0, 1, 103,                     // "g" as atom
+            97, 128,                            // 128
+
+            100, 0, 1, 98,                      // "b" as atom
+            97, 128                             // 128
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_map_of_unknown_length() {
+
+    #[derive(Serialize)]
+    struct Point {
+        x: u8,
+        #[serde(flatten)]
+        extra: BTreeMap<&'static str, u8>,
+    }
+    let mut extra = BTreeMap::new();
+    extra.insert("y", 2);
+    let point = Point{x: 1, extra};
+
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        ..SerializerOptions::default()
+    };
+    assert_eq!(
+        term_to_binary_with_options(&point, options).unwrap(),
+        vec![
+            131u8,
+            116,                                // map
+            0, 0, 0, 2,                         // arity
+            107, 0, 1, 120,                     // "x" as string
+            97, 1,                              // 1
+            107, 0, 1, 121,                     // "y" as string
+            97, 2                               // 2
+        ]
+    );
+
+    assert_eq!(
+        term_to_binary(&point).unwrap(),
+        vec![
+            131u8,
+            104,                                // small tuple
+            3,                                  // arity
+            100, 0, 4, 98, 101, 114, 116,       // "bert" as atom
+            100, 0, 4, 100, 105, 99, 116,       // "dict" as atom
+            108,                                // list
+            0, 0, 0, 2,                         // length
+            104, 2, 107, 0, 1, 120, 97, 1,      // {"x", 1}
+            104, 2, 107, 0, 1, 121, 97, 2,      // {"y", 2}
+            106                                 // nil
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_deterministic_map() {
+    let mut map: HashMap<String, u8> = HashMap::new();
+    for (index, key) in ["b", "ab", "a", "c", "", "ba"].iter().enumerate() {
+        map.insert(key.to_string(), index as u8);
+    }
+
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        deterministic: true,
+        ..SerializerOptions::default()
+    };
+    assert_eq!(
+        term_to_binary_with_options(&map, options).unwrap(),
+        vec![
+            131u8,
+            116,                                // map
+            0, 0, 0, 6,                         // arity
+            107, 0, 0, 97, 4,                   // "" => 4
+            107, 0, 1, 97, 97, 2,               // "a" => 2
+            107, 0, 2, 97, 98, 97, 1,           // "ab" => 1
+            107, 0, 1, 98, 97, 0,               // "b" => 0
+            107, 0, 2, 98, 97, 97, 5,           // "ba" => 5
+            107, 0, 1, 99, 97, 3,               // "c" => 3
+        ]
+    );
+
+    let mut map: HashMap<i64, u8> = HashMap::new();
+    map.insert(300, 1);
+    map.insert(-1, 2);
+    map.insert(1 << 40, 3);
+    map.insert(7, 4);
+
+    let options = SerializerOptions {
+        deterministic: true,
+        ..SerializerOptions::default()
+    };
+    assert_eq!(
+        term_to_binary_with_options(&map, options).unwrap(),
+        vec![
+            131u8,
+            104,                                // small tuple
+            3,                                  // arity
+            100, 0, 4, 98, 101, 114, 116,       // "bert" as atom
+            100, 0, 4, 100, 105, 99, 116,       // "dict" as atom
+            108,                                // list
+            0, 0, 0, 4,                         // length
+            104, 2, 98, 255, 255, 255, 255, 97, 2,  // {-1, 2}
+            104, 2, 97, 7, 97, 4,               // {7, 4}
+            104, 2, 98, 0, 0, 1, 44, 97, 1,     // {300, 1}
+            104, 2, 110, 6, 0, 0, 0, 0, 0, 0, 1, 97, 3,  // {1 bsl 40, 3}
+            106                                 // nil
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_deterministic_struct() {
+    #[derive(Serialize)]
+    struct Color {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+    let color = Color{r: 1, g: 2, b: 3};
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        deterministic: true,
+        ..SerializerOptions::default()
+    };
+
+    assert_eq!(
+        term_to_binary_with_options(&color, options).unwrap(),
+        vec![
+            131u8,
+            116,                                // map
+            0, 0, 0, 3,                         // arity
+            100, 0, 1, 98, 97, 3,               // b => 3
+            100, 0, 1, 103, 97, 2,              // g => 2
+            100, 0, 1, 114, 97, 1,              // r => 1
+        ]
+    );
+}
+
+
 #[test]
 fn test_serialize_bignum() {
     let positive_bignum = BertBigInteger(BigInt::from(1000i32));
@@ -595,6 +1217,38 @@ fn test_serialize_bignum() {
 }
 
 
+#[test]
+fn test_serialize_struct_with_64bit_fields() {
+
+    #[derive(Serialize)]
+    struct Event {
+        id: u64,
+        at: i64,
+    }
+    let event = Event{id: 7, at: 1_700_000_000_000};
+
+    assert_eq!(
+        term_to_binary(&event).unwrap(),
+        vec![
+            131u8,
+            105,                                // tuple
+            0, 0, 0, 3,                         // length
+            100, 0, 5, 101, 118, 101, 110, 116, // "event" as atom
+
+            104,                                // tuple
+            2,                                  // length
+            100, 0, 2, 105, 100,                // "id" as atom
+            97, 7,                              // 7
+
+            104,                                // tuple
+            2,                                  // length
+            100, 0, 2, 97, 116,                 // "at" as atom
+            110, 6, 0, 0, 104, 229, 207, 139, 1 // 1700000000000
+        ]
+    );
+}
+
+
 #[test]
 fn test_serialize_bert_time() {
     let time = BertTime::new(1255, 295581, 446228);
@@ -640,3 +1294,250 @@ fn test_serialize_bert_regex() {
         ]
     )
 }
+
+
+#[test]
+fn test_serialize_pid() {
+    let pid = BertPid::new("App@Host", 85, 0, 1706800000);
+
+    assert_eq!(
+        term_to_binary(&pid).unwrap(),
+        vec![
+            131u8,
+            88,                                          // new pid
+            100, 0, 8, 65, 112, 112, 64, 72, 111, 115, 116, // "App@Host" as atom
+            0, 0, 0, 85,                                 // id
+            0, 0, 0, 0,                                  // serial
+            101, 187, 179, 128                           // creation
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_reference() {
+    let reference = BertReference::new("a@host", 1706800000, vec![155202, 2214330370, 1519]);
+
+    assert_eq!(
+        term_to_binary(&reference).unwrap(),
+        vec![
+            131u8,
+            90,                                          // newer reference
+            0, 3,                                        // number of id words
+            100, 0, 6, 97, 64, 104, 111, 115, 116,       // "a@host" as atom
+            101, 187, 179, 128,                          // creation
+            0, 2, 94, 66,                                // id words
+            131, 252, 0, 2,
+            0, 0, 5, 239
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_reference_invalid_id() {
+    let reference = BertReference::new("a@host", 1, vec![]);
+    assert!(matches!(term_to_binary(&reference), Err(Error::Custom(_))));
+
+    let reference = BertReference::new("a@host", 1, vec![0; 70000]);
+    assert!(matches!(term_to_binary(&reference), Err(Error::Custom(_))));
+
+    let reference = BertReference::new("a@host", 1, vec![0; 5]);
+    assert!(term_to_binary(&reference).is_ok());
+}
+
+
+#[test]
+fn test_serialize_port() {
+    let port = BertPort::new("a@host", 17, 3);
+
+    assert_eq!(
+        term_to_binary(&port).unwrap(),
+        vec![
+            131u8,
+            89,                                          // new port
+            100, 0, 6, 97, 64, 104, 111, 115, 116,       // "a@host" as atom
+            0, 0, 0, 17,                                 // id
+            0, 0, 0, 3                                   // creation
+        ]
+    );
+
+    let port = BertPort::new("a@host", 4294967296, 3);
+
+    assert_eq!(
+        term_to_binary(&port).unwrap(),
+        vec![
+            131u8,
+            120,                                         // v4 port
+            100, 0, 6, 97, 64, 104, 111, 115, 116,       // "a@host" as atom
+            0, 0, 0, 1, 0, 0, 0, 0,                      // id
+            0, 0, 0, 3                                   // creation
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_bitstring() {
+    // <<5:3>> in Erlang
+    let bitstring = BertBitString::new(vec![160], 3);
+
+    assert_eq!(
+        term_to_binary(&bitstring).unwrap(),
+        vec![
+            131u8,
+            77,                                          // bit binary
+            0, 0, 0, 1,                                  // length
+            3,                                           // bits in the last byte
+            160                                          // 0b101_00000
+        ]
+    );
+
+    // Unused bits of the last byte are written as zeros
+    let bitstring = BertBitString::new(vec![191], 3);
+    assert_eq!(vec![131u8, 77, 0, 0, 0, 1, 3, 160], term_to_binary(&bitstring).unwrap());
+
+    // <<>> is the only bitstring with 0 bits in the last byte
+    let bitstring = BertBitString::new(vec![], 0);
+    assert_eq!(vec![131u8, 77, 0, 0, 0, 0, 0], term_to_binary(&bitstring).unwrap());
+}
+
+
+#[test]
+fn test_serialize_invalid_bitstring() {
+    let bitstring = BertBitString::new(vec![1, 2], 0);
+    assert!(matches!(term_to_binary(&bitstring), Err(Error::Custom(_))));
+
+    let bitstring = BertBitString::new(vec![1, 2], 12);
+    assert!(matches!(term_to_binary(&bitstring), Err(Error::Custom(_))));
+
+    assert!(matches!(BertBitString::try_new(vec![1, 2], 0), Err(Error::InvalidBitCount(0))));
+    assert!(matches!(BertBitString::try_new(vec![1, 2], 12), Err(Error::InvalidBitCount(12))));
+    assert!(BertBitString::try_new(vec![], 0).is_ok());
+}
+
+
+#[test]
+fn test_serialize_export_fun() {
+    // fun mymod:run/1 in Erlang
+    let fun = BertExportFun::new("mymod", "run", 1);
+
+    assert_eq!(
+        term_to_binary(&fun).unwrap(),
+        vec![
+            131u8,
+            113,                                         // export
+            100, 0, 5, 109, 121, 109, 111, 100,          // "mymod" as atom
+            100, 0, 3, 114, 117, 110,                    // "run" as atom
+            97, 1                                        // arity
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_closure() {
+    let closure = BertClosure {
+        arity: 0,
+        uniq: [0; 16],
+        index: 2,
+        module: vec![100, 0, 1, 109],
+        old_index: vec![97, 2],
+        old_uniq: vec![97, 3],
+        pid: vec![88, 100, 0, 1, 110, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0],
+        free_vars: vec![vec![106]],
+    };
+
+    assert_eq!(
+        term_to_binary(&closure).unwrap(),
+        vec![
+            131u8,
+            112,                                         // new fun
+            0, 0, 0, 55,                                 // size
+            0,                                           // arity
+            0, 0, 0, 0, 0, 0, 0, 0,                      // uniq
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 2,                                  // index
+            0, 0, 0, 1,                                  // number of free variables
+            100, 0, 1, 109,                              // module
+            97, 2,                                       // old index
+            97, 3,                                       // old uniq
+            88, 100, 0, 1, 110,                          // pid
+            0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0,
+            106                                          // free variable
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_improper_list() {
+    // [1, 2 | 3] in Erlang
+    let list = ImproperList::new(vec![1u8, 2], 3u8);
+
+    assert_eq!(
+        term_to_binary(&list).unwrap(),
+        vec![
+            131u8,
+            108,                                         // list
+            0, 0, 0, 2,                                  // length
+            97, 1,                                       // 1
+            97, 2,                                       // 2
+            97, 3                                        // tail
+        ]
+    );
+}
+
+
+#[test]
+fn test_serialize_compressed() {
+    let value = vec![1u8; 20];
+
+    let data = term_to_binary_compressed(&value, 6).unwrap();
+    assert_eq!(
+        data[..6].to_vec(),
+        vec![
+            131u8,
+            80,                                          // compressed
+            0, 0, 0, 46                                  // uncompressed size
+        ]
+    );
+    assert_eq!(120, data[6]);                            // zlib header
+}
+
+
+#[test]
+fn test_serialize_compressed_keeps_small_terms_uncompressed() {
+    let value = (1u8, 2u8);
+
+    assert_eq!(
+        term_to_binary_compressed(&value, 9).unwrap(),
+        term_to_binary(&value).unwrap()
+    );
+}
+
+
+#[test]
+fn test_serialize_compressed_with_options() {
+    let value: HashMap<String, u8> = (0..50).map(|index| (format!("key{}", index), 1)).collect();
+    let options = SerializerOptions {
+        map_encoding: MapEncoding::Native,
+        deterministic: true,
+        ..SerializerOptions::default()
+    };
+
+    let data = term_to_binary_compressed_with_options(&value, 6, options.clone()).unwrap();
+    assert_eq!(80, data[1]);
+    let result: HashMap<String, u8> = bert::binary_to_term(&data).unwrap();
+    assert_eq!(value, result);
+
+    // Sorted entries give the same bytes regardless of the hash map order
+    let copy: HashMap<String, u8> = value.clone().into_iter().collect();
+    assert_eq!(data, term_to_binary_compressed_with_options(&copy, 6, options).unwrap());
+}
+
+
+#[test]
+fn test_serialize_compressed_invalid_level() {
+    assert!(term_to_binary_compressed(&1u8, 10).is_err());
+}
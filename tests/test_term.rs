@@ -0,0 +1,238 @@
+use std::cmp::Ordering;
+
+use bert::{
+    ETF_VERSION, Term, BertPid, BertReference, BertBitString, Error,
+    term_to_binary, term_to_binary_compressed, binary_to_term
+};
+use num::bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+
+#[test]
+fn test_term_from_bytes() {
+    // {ok, [1, 2], <<"bin">>, #{a => 1.5}}
+    let data = vec![
+        ETF_VERSION,
+        104, 4,                                      // SmallTuple, arity 4
+        119, 2, 111, 107,                            // ok
+        107, 0, 2, 1, 2,                             // [1, 2]
+        109, 0, 0, 0, 3, 98, 105, 110,               // <<"bin">>
+        116, 0, 0, 0, 1,                             // Map, arity 1
+        119, 1, 97,                                  // a
+        70, 63, 248, 0, 0, 0, 0, 0, 0,               // 1.5
+    ];
+    let term = Term::from_bytes(&data).unwrap();
+
+    assert_eq!(
+        Term::Tuple(vec![
+            Term::Atom("ok".to_string()),
+            Term::String(vec![1, 2]),
+            Term::Binary(b"bin".to_vec()),
+            Term::Map(vec![(Term::Atom("a".to_string()), Term::Float(1.5))]),
+        ]),
+        term
+    );
+    assert_eq!(data, term.to_bytes().unwrap());
+}
+
+
+#[test]
+fn test_term_roundtrip_erlang_outputs() {
+    let corpus: Vec<Vec<u8>> = vec![
+        // -1
+        vec![ETF_VERSION, 98, 255, 255, 255, 255],
+        // 1 bsl 64
+        vec![ETF_VERSION, 110, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1],
+        // ok, as OTP 26 writes it
+        vec![ETF_VERSION, 119, 2, 111, 107],
+        // 'café'
+        vec![ETF_VERSION, 119, 5, 99, 97, 102, 195, 169],
+        // 'ключ'
+        vec![ETF_VERSION, 119, 8, 208, 186, 208, 187, 209, 142, 209, 135],
+        // []
+        vec![ETF_VERSION, 106],
+        // [1, 2, 3]
+        vec![ETF_VERSION, 107, 0, 3, 1, 2, 3],
+        // [a | b]
+        vec![ETF_VERSION, 108, 0, 0, 0, 1, 119, 1, 97, 119, 1, 98],
+        // [{}, 256]
+        vec![ETF_VERSION, 108, 0, 0, 0, 2, 104, 0, 98, 0, 0, 1, 0, 106],
+        // {<0.85.0>, #Ref<0.1.2.3>} from nonode@nohost
+        vec![
+            ETF_VERSION, 104, 2,
+            88, 119, 13, 110, 111, 110, 111, 100, 101, 64, 110, 111, 104, 111, 115, 116,
+            0, 0, 0, 85, 0, 0, 0, 0, 0, 0, 0, 0,
+            90, 0, 3, 119, 13, 110, 111, 110, 111, 100, 101, 64, 110, 111, 104, 111, 115, 116,
+            0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 1,
+        ],
+        // <0.40.1> from a@host, as PID_EXT with an ATOM_EXT node
+        vec![
+            ETF_VERSION, 103, 100, 0, 6, 97, 64, 104, 111, 115, 116,
+            0, 0, 0, 40, 0, 0, 0, 1, 2,
+        ],
+        // <<1:1>>
+        vec![ETF_VERSION, 77, 0, 0, 0, 1, 1, 128],
+        // fun lists:reverse/1
+        vec![
+            ETF_VERSION, 113,
+            119, 5, 108, 105, 115, 116, 115,
+            119, 7, 114, 101, 118, 101, 114, 115, 101,
+            97, 1,
+        ],
+    ];
+
+    for data in corpus {
+        let term = Term::from_bytes(&data).unwrap();
+        assert_eq!(data, term.to_bytes().unwrap());
+    }
+
+    // Releases before OTP 26 write Latin-1 atoms as ATOM_EXT
+    let term = Term::from_bytes(&[ETF_VERSION, 100, 0, 4, 99, 97, 102, 233]).unwrap();
+    assert_eq!(Term::Atom("café".to_string()), term);
+    assert_eq!(vec![ETF_VERSION, 119, 5, 99, 97, 102, 195, 169], term.to_bytes().unwrap());
+
+    // Lists of bytes are STRING_EXT, as Erlang writes them, and decoded
+    // strings are equal to them
+    let term = Term::List(vec![Term::Integer(1), Term::Integer(2), Term::Integer(3)]);
+    assert_eq!(vec![ETF_VERSION, 107, 0, 3, 1, 2, 3], term.to_bytes().unwrap());
+    assert_eq!(term, Term::from_bytes(&term.to_bytes().unwrap()).unwrap());
+    assert_eq!(Term::String(vec![1, 2, 3]), term);
+}
+
+
+#[test]
+fn test_term_integers() {
+    // Bignum which fits into i64
+    let data = vec![ETF_VERSION, 110, 4, 1, 0, 0, 0, 128];
+    assert_eq!(Term::Integer(-2147483648), Term::from_bytes(&data).unwrap());
+
+    let big = BigInt::from(1u128 << 100);
+    let term = Term::BigInteger(big.clone());
+    assert_eq!(term, Term::from_bytes(&term.to_bytes().unwrap()).unwrap());
+
+    assert_eq!(vec![ETF_VERSION, 97, 255], Term::Integer(255).to_bytes().unwrap());
+    assert_eq!(
+        vec![ETF_VERSION, 110, 5, 0, 0, 0, 0, 0, 1],
+        Term::Integer(1 << 32).to_bytes().unwrap()
+    );
+}
+
+
+#[test]
+fn test_term_erlang_types() {
+    let term = Term::List(vec![
+        Term::Pid(BertPid::new("a@host", 85, 0, 3)),
+        Term::Reference(BertReference::new("a@host", 3, vec![1, 2, 3])),
+        Term::BitString(BertBitString::new(vec![255, 128], 1)),
+        Term::ImproperList(vec![Term::Integer(1)], Box::new(Term::Atom("tail".to_string()))),
+    ]);
+    let data = term.to_bytes().unwrap();
+    assert_eq!(term, Term::from_bytes(&data).unwrap());
+}
+
+
+#[test]
+fn test_term_from_compressed_bytes() {
+    let value = "a".repeat(100);
+    let data = term_to_binary_compressed(&value, 6).unwrap();
+    assert_eq!(80, data[1]);
+    assert_eq!(Term::String(vec![97; 100]), Term::from_bytes(&data).unwrap());
+}
+
+
+#[test]
+fn test_term_from_invalid_bytes() {
+    assert!(matches!(Term::from_bytes(&[130, 106]), Err(Error::InvalidVersionNumber)));
+    assert!(matches!(Term::from_bytes(&[ETF_VERSION, 106, 106]), Err(Error::TrailingBytes)));
+    assert!(matches!(Term::from_bytes(&[ETF_VERSION, 200]), Err(Error::InvalidTag)));
+}
+
+
+#[test]
+fn test_term_from_hostile_bytes() {
+    // Lengths which are far beyond the input don't allocate memory for them
+    assert!(Term::from_bytes(&[ETF_VERSION, 116, 255, 255, 255, 255]).is_err());
+    assert!(Term::from_bytes(&[ETF_VERSION, 109, 255, 255, 255, 255, 1]).is_err());
+    assert!(Term::from_bytes(&[ETF_VERSION, 108, 255, 255, 255, 255, 97, 1]).is_err());
+
+    // {{{...}}} nested deeper than the stack allows
+    let mut data = vec![ETF_VERSION];
+    for _ in 0..2_000_000 {
+        data.extend([104, 1]);
+    }
+    data.push(106);
+    assert!(matches!(Term::from_bytes(&data), Err(Error::NestingTooDeep)));
+
+    let mut data = vec![ETF_VERSION];
+    for _ in 0..120 {
+        data.extend([104, 1]);
+    }
+    data.push(106);
+    assert_eq!(data, Term::from_bytes(&data).unwrap().to_bytes().unwrap());
+}
+
+
+#[test]
+fn test_term_serde() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        id: u8,
+        payload: Term,
+    }
+
+    let payload = Term::Tuple(vec![
+        Term::Atom("reply".to_string()),
+        Term::List(vec![Term::Integer(-5), Term::Float(0.5)]),
+    ]);
+    let message = Message { id: 1, payload: payload.clone() };
+    let data = term_to_binary(&message).unwrap();
+
+    // The term is nested as-is
+    let mut expected = payload.to_bytes().unwrap();
+    expected.remove(0);
+    assert!(data.windows(expected.len()).any(|window| window == expected.as_slice()));
+
+    let result: Message = binary_to_term(&data).unwrap();
+    assert_eq!(message, result);
+
+    // Any value can be decoded as a term
+    let data = term_to_binary(&(1u8, "text")).unwrap();
+    let result: Term = binary_to_term(&data).unwrap();
+    assert_eq!(
+        Term::Tuple(vec![Term::Integer(1), Term::String(b"text".to_vec())]),
+        result
+    );
+}
+
+
+#[test]
+fn test_term_exact_equality() {
+    assert_ne!(Term::Integer(1), Term::Float(1.0));
+    assert_eq!(Term::Binary(vec![1]), Term::BitString(BertBitString::new(vec![1], 8)));
+    assert_eq!(
+        Term::Map(vec![(Term::Integer(1), Term::Integer(2)), (Term::Integer(3), Term::Integer(4))]),
+        Term::Map(vec![(Term::Integer(3), Term::Integer(4)), (Term::Integer(1), Term::Integer(2))])
+    );
+    assert_eq!(
+        Term::ImproperList(vec![], Box::new(Term::String(vec![1]))),
+        Term::List(vec![Term::Integer(1)])
+    );
+}
+
+
+#[test]
+fn test_term_compare() {
+    let one = Term::Integer(1);
+    let atom = Term::Atom("a".to_string());
+    let tuple = Term::Tuple(vec![]);
+    let nil = Term::List(vec![]);
+    let string = Term::String(vec![1]);
+    let list = Term::List(vec![Term::Integer(1), Term::Integer(2)]);
+
+    assert_eq!(Ordering::Equal, one.compare(&Term::Float(1.0)));
+    assert_eq!(Ordering::Less, one.compare(&atom));
+    assert_eq!(Ordering::Less, atom.compare(&tuple));
+    assert_eq!(Ordering::Less, tuple.compare(&nil));
+    assert_eq!(Ordering::Less, nil.compare(&string));
+    assert_eq!(Ordering::Less, string.compare(&list));
+}
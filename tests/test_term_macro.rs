@@ -0,0 +1,72 @@
+use bert::{ETF_VERSION, Term, BertPid, term, term_bytes, term_to_binary};
+use serde::Serialize;
+
+
+#[test]
+fn test_term_macro() {
+    let value = 42;
+    let term = term!{ {ok, [1, -2, <<"bin">>], #{key => (value)}} };
+
+    assert_eq!(
+        Term::Tuple(vec![
+            Term::Atom("ok".to_string()),
+            Term::List(vec![Term::Integer(1), Term::Integer(-2), Term::Binary(b"bin".to_vec())]),
+            Term::Map(vec![(Term::Atom("key".to_string()), Term::Integer(42))]),
+        ]),
+        term
+    );
+}
+
+
+#[test]
+fn test_term_macro_values() {
+    assert_eq!(Term::Atom("true".to_string()), term!(true));
+    assert_eq!(Term::Float(-1.5), term!(-1.5));
+    assert_eq!(Term::String(b"abc".to_vec()), term!("abc"));
+    assert_eq!(Term::Integer(i64::MAX), term!(9223372036854775807i64));
+    assert_eq!(Term::Tuple(vec![]), term!({}));
+    assert_eq!(Term::List(vec![]), term!([]));
+    assert_eq!(Term::Map(vec![]), term!(#{}));
+    assert_eq!(Term::Binary(vec![]), term!(<<>>));
+
+    let bytes = vec![3, 4];
+    assert_eq!(Term::Binary(vec![97, 1, 2, 3, 4]), term!(<<"a", 1, 2, (&bytes)>>));
+
+    let pid = BertPid::new("nonode@nohost", 1, 0, 0);
+    assert_eq!(
+        Term::ImproperList(
+            vec![Term::Pid(pid.clone()), Term::Tuple(vec![Term::Integer(1)])],
+            Box::new(Term::Atom("tail".to_string()))
+        ),
+        term!([(pid), {1,} | tail])
+    );
+
+    assert_eq!(
+        Term::Map(vec![
+            (Term::Binary(b"a".to_vec()), Term::Map(vec![])),
+            (Term::Integer(-1), Term::List(vec![Term::Atom("x".to_string())])),
+        ]),
+        term!(#{<<"a">> => #{}, -1 => [x],})
+    );
+}
+
+
+#[test]
+fn test_term_bytes_macro() {
+    #[derive(Serialize)]
+    struct Point(i32, i32);
+
+    let expected = vec![
+        ETF_VERSION,
+        104, 3,                                      // SmallTuple, arity 3
+        119, 5, 112, 111, 105, 110, 116,             // point
+        97, 1,                                       // 1
+        97, 2,                                       // 2
+    ];
+    assert_eq!(expected, term_bytes!{ {point, 1, 2} }.unwrap());
+
+    // The serializer writes small integers as INTEGER_EXT and tuples as
+    // LARGE_TUPLE_EXT, so the terms are compared after decoding
+    let serialized = term_to_binary(&Point(1, 2)).unwrap();
+    assert_eq!(term!{ {point, 1, 2} }, Term::from_bytes(&serialized).unwrap());
+}
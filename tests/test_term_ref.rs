@@ -0,0 +1,131 @@
+use bert::{ETF_VERSION, BertTag, Term, TermRef, Error};
+
+
+// {ok, [1, foo | bar], <<"bin">>, #{a => 1.5, 2 => 'ключ'}}
+fn get_data() -> Vec<u8> {
+    vec![
+        ETF_VERSION,
+        104, 4,                                      // SmallTuple, arity 4
+        100, 0, 2, 111, 107,                         // ok
+        108, 0, 0, 0, 2,                             // List, length 2
+        97, 1,                                       // 1
+        115, 3, 102, 111, 111,                       // foo
+        100, 0, 3, 98, 97, 114,                      // bar
+        109, 0, 0, 0, 3, 98, 105, 110,               // <<"bin">>
+        116, 0, 0, 0, 2,                             // Map, arity 2
+        100, 0, 1, 97,                               // a
+        70, 63, 248, 0, 0, 0, 0, 0, 0,               // 1.5
+        97, 2,                                       // 2
+        119, 8, 208, 186, 208, 187, 209, 142, 209, 135, // 'ключ'
+    ]
+}
+
+
+#[test]
+fn test_term_ref_tuple() {
+    let data = get_data();
+    let term = TermRef::new(&data).unwrap();
+
+    assert_eq!(BertTag::SmallTuple, term.tag().unwrap());
+    assert_eq!(4, term.len().unwrap());
+    assert_eq!(&data[1..], term.as_bytes().unwrap());
+
+    let first = term.element(0).unwrap().unwrap();
+    assert!(first.is_atom("ok"));
+    assert_eq!(b"ok", first.as_atom().unwrap());
+
+    let binary = term.element(2).unwrap().unwrap();
+    assert_eq!(b"bin", binary.as_binary().unwrap());
+    // The binary is borrowed from the data
+    assert_eq!(data[31..].as_ptr(), binary.as_binary().unwrap().as_ptr());
+
+    assert_eq!(None, term.element(4).unwrap());
+    assert_eq!(Term::from_bytes(&data).unwrap(), term.to_term().unwrap());
+}
+
+
+#[test]
+fn test_term_ref_list() {
+    let data = get_data();
+    let list = TermRef::new(&data).unwrap().element(1).unwrap().unwrap();
+
+    let elements = list.elements().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(2, elements.len());
+    assert_eq!(1, elements[0].as_integer().unwrap());
+    assert!(elements[1].is_atom("foo"));
+    assert!(list.tail().unwrap().is_atom("bar"));
+
+    // [1, 2]
+    let data = [ETF_VERSION, 107, 0, 2, 1, 2];
+    let string = TermRef::new(&data).unwrap();
+    assert_eq!(&[1, 2], string.as_string().unwrap());
+    assert_eq!(BertTag::Nil, string.tail().unwrap().tag().unwrap());
+
+    // Bytes of the string are elements like in any other list
+    let elements = string.elements().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(2, elements.len());
+    assert_eq!(1, elements[0].as_integer().unwrap());
+    assert_eq!(2, string.element(1).unwrap().unwrap().as_integer().unwrap());
+    assert_eq!(BertTag::SmallInteger, string.element(1).unwrap().unwrap().tag().unwrap());
+    assert_eq!(None, string.element(2).unwrap());
+    assert!(TermRef::new(&[ETF_VERSION, 107, 0, 2, 1]).unwrap().element(1).is_err());
+}
+
+
+#[test]
+fn test_term_ref_map() {
+    let data = get_data();
+    let map = TermRef::new(&data).unwrap().element(3).unwrap().unwrap();
+
+    assert_eq!(2, map.len().unwrap());
+    let value = map.get(&Term::Atom("a".to_string())).unwrap().unwrap();
+    assert_eq!(1.5, value.as_float().unwrap());
+
+    let value = map.get(&Term::Integer(2)).unwrap().unwrap();
+    assert!(value.is_atom("ключ"));
+    assert_eq!("ключ".as_bytes(), value.as_atom().unwrap());
+
+    assert_eq!(None, map.get(&Term::Float(2.0)).unwrap());
+    assert_eq!(None, map.get(&Term::Atom("b".to_string())).unwrap());
+
+    let keys = map.entries().unwrap()
+        .map(|entry| entry.and_then(|(key, _)| key.to_term()))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(vec![Term::Atom("a".to_string()), Term::Integer(2)], keys);
+}
+
+
+#[test]
+fn test_term_ref_errors() {
+    let data = get_data();
+    let term = TermRef::new(&data).unwrap();
+
+    assert!(matches!(term.as_binary(), Err(Error::InvalidTag)));
+    assert!(matches!(term.entries(), Err(Error::InvalidTag)));
+    assert!(matches!(TermRef::new(&[130, 106]), Err(Error::InvalidVersionNumber)));
+
+    // The tuple is cut after the first element
+    let truncated = TermRef::new(&data[..8]).unwrap();
+    assert!(truncated.element(0).unwrap().unwrap().is_atom("ok"));
+    assert!(matches!(truncated.element(1), Err(Error::EndOfStream)));
+}
+
+
+#[test]
+fn test_term_ref_deeply_nested() {
+    // {{{...}}} with two million tuples
+    let mut data = vec![ETF_VERSION];
+    for _ in 0..2_000_000 {
+        data.extend([104, 1]);
+    }
+    data.push(106);
+    let term = TermRef::new(&data).unwrap();
+    assert_eq!(&data[1..], term.as_bytes().unwrap());
+    assert_eq!(1, term.len().unwrap());
+
+    // The innermost tuple is missing
+    data.pop();
+    let term = TermRef::new(&data).unwrap();
+    assert!(matches!(term.as_bytes(), Err(Error::EndOfStream)));
+}